@@ -1,146 +1,668 @@
 use crate::Plot;
 use crate::HexBoard;
+use crate::hexboard::InitialState;
+use crate::tracking;
+use crate::statistics::{self, ErrorMethod, Estimate, Sampling};
+use crate::equilibration::{Criterion, Equilibration};
+use crate::sweep::{Parameter, Parameters, PointRecord, Sweep, Values};
+use crate::phase::Phase;
+use crate::checkpoint::{self, Reader, Writer};
+use crate::series::{Format, Timeseries};
+use crate::error::{report, Error, Result};
+use crate::observer::{self, Histograms, Observer, Snapshots};
+use crate::random::{Backend, Generator};
+use crate::exact;
 
 use rand::Rng;
-use rand::rngs::StdRng;
-use rand::SeedableRng;
 use plotters::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::fs;
 
 pub struct Batchrun {
-    runs_number: i32,
-    title: String
+    title: String,
+    burnin: Option<Equilibration>,
+    replicas: i32,
+    error_method: ErrorMethod,
+    threads: usize,
+    checkpoints: Option<(String, i32)>,
+    output: Option<(String, Format)>,
+    realisations: i32,
+    substrate_seed: u64,
+    backend: Backend,
+    seed: u64,
+    histograms: Option<f64>,
+    snapshots: Option<(String, i32)>,
+}
+
+//measurements of one parameter point, averaged over its replicas, with the timeseries used by the plots
+struct PointResult {
+    record: PointRecord,
+    order: Vec<Vec<(i32, f64)>>,
+    psi_band: Vec<(i32, f64, f64)>,
+    histograms: Option<Histograms>,
+}
+
+//everything needed to continue a replica after an interruption: the board and the samples measured so far
+struct ReplicaState {
+    board: HexBoard,
+    step: i32,
+    samples: Samples,
+}
+
+impl ReplicaState {
+    fn new(board: HexBoard) -> Self {
+        let samples = Samples::new(board.get_sublattice_count());
+        return ReplicaState{board, step: 0, samples};
+    }
+}
+
+//observer recording the measurements of a replica once per sweep, the sublattice orders only when keep_order is set
+struct Samples {
+    keep_order: bool,
+    order: Vec<Vec<(i32, f64)>>,
+    energy: Vec<f64>,
+    //the full hamiltonian of exact::energy, field and on-site energies included
+    hamiltonian: Vec<f64>,
+    psi: Vec<(i32, f64)>,
+    sublattices: Vec<f64>,
+}
+
+impl Samples {
+    fn new(sublattices: usize) -> Self {
+        return Samples{keep_order: false, order: vec![vec![]; sublattices], energy: vec![], hamiltonian: vec![], psi: vec![], sublattices: vec![0.0; sublattices]};
+    }
+}
+
+impl Observer for Samples {
+    fn on_sweep(&mut self, board: &HexBoard, step: i32) {
+        let run_orders = board.get_order();
+        if self.keep_order {
+            for (series, &value) in self.order.iter_mut().zip(run_orders.iter()) {
+                series.push((step, value as f64));
+            }
+        }
+        self.energy.push(board.total_energy());
+        self.hamiltonian.push(exact::energy(board));
+        self.psi.push((step / (board.size as i32 * board.size as i32), board.get_order_parameter()));
+        let mut sorted_orders = run_orders;
+        sorted_orders.sort_by(|a, b| b.total_cmp(a));
+        for (total, value) in self.sublattices.iter_mut().zip(sorted_orders) {
+            *total += value as f64;
+        }
+    }
+}
+
+//specific heat per site C = beta^2 var(H) / N from samples of the hamiltonian, N counting the sites of the lattice
+pub(crate) fn specific_heat(error_method: ErrorMethod, hamiltonian: &[f64], betaj: f64, sites: usize) -> Estimate {
+    return error_method.estimate_with(hamiltonian, statistics::variance).scale(betaj * betaj / sites as f64);
+}
+
+//measurements of a single replica of a parameter point
+struct ReplicaResult {
+    order: Vec<Vec<(i32, f64)>>,
+    psi: Vec<(i32, f64)>,
+    psi_estimate: Estimate,
+    energy_estimate: Estimate,
+    specific_heat: Estimate,
+    susceptibility: Estimate,
+    sublattices: Vec<f64>,
+    acceptance_rate: f64,
+    histograms: Option<Histograms>,
 }
 
 impl Batchrun {
 
     //constructor
-    pub fn new(number: i32, title: &str) -> Self {
-        return Batchrun{runs_number: number, title: title.to_string()};
+    pub fn new(title: &str) -> Self {
+        return Batchrun{title: title.to_string(), burnin: None, replicas: 1, error_method: ErrorMethod::Blocking, threads: 1, checkpoints: None, output: None, realisations: 1, substrate_seed: 1357924680,
+            backend: Backend::ChaCha12, seed: 3463462432, histograms: None, snapshots: None};
     }
 
-    //batch runner
-    pub fn fill_test(&self, matrix_size: u16, betaj: f64, sim_lenght: i32, min_fill: f64, max_fill: f64) {
+    //accumulates histograms of the energy, |psi| and sublattice fractions sampled by the replicas of every point after
+    //their burn-in, with energy bins energy_width wide. the histograms of a point are written to "{title} point {i} histograms.csv"
+    //and drawn to "{title} point {i} energy histogram.svg" and "{title} point {i} psi histogram.svg". fails unless energy_width
+    //is positive and finite
+    pub fn set_histograms(&mut self, energy_width: f64) -> Result<()> {
+        if !(energy_width.is_finite() && energy_width > 0.0) {
+            return Err(Error::Parameter(format!("the energy bins of the histograms must have a positive width, not {}", energy_width)));
+        }
+        self.histograms = Some(energy_width);
+        return Ok(());
+    }
 
-        println!("> Beginning fill test");
+    //sets the random generator of the replicas and the seed they are split from: replica r of point p of a sweep runs on
+    //stream p * 2^32 + r of seed, so adding points or replicas leaves the streams of the others unchanged
+    pub fn set_generator(&mut self, backend: Backend, seed: u64) {
+        self.backend = backend;
+        self.seed = seed;
+    }
 
-        let mut rng: StdRng = StdRng::seed_from_u64(3463462432);
-        let order_plot = Plot::new(500, 500, 0..sim_lenght, 0.0..1.0);
-        let mut orders: Vec<Vec<Vec<(i32, f64)>>> = vec![];
-        let fillpercentage = self.gen_range(self.runs_number as f64, min_fill, max_fill);
-
-        for run in 0..self.runs_number {
-            
-            let mut board = HexBoard::new(
-                matrix_size,
-                StdRng::seed_from_u64(rng.gen()),
-                betaj,
-                fillpercentage[run as usize] as f32,
-            );
-            let mut order: Vec<Vec<(i32, f64)>> = vec![vec![], vec![], vec![]];
+    //the stream of replica of point
+    fn stream(point: usize, replica: i32) -> u64 {
+        return (point as u64) << 32 | replica as u64;
+    }
 
-            board.initialize();
+    //sets how many independent replicas are run for every parameter point and how their errors are computed
+    pub fn set_replicas(&mut self, replicas: i32, error_method: ErrorMethod) {
+        self.replicas = replicas.max(1);
+        self.error_method = error_method;
+    }
 
-            for x in 0..sim_lenght {
-                board.advance_timestep_repulsive();
-                let run_orders = board.get_order();
-                order[0].push((x, run_orders[0] as f64));
-                order[1].push((x, run_orders[1] as f64));
-                order[2].push((x, run_orders[2] as f64));
+    //sets over how many realisations of the substrate every point is averaged, each of them running every replica.
+    //the realisations are drawn from seed, independently of the thermal seeds, and are the same at every point
+    pub fn set_realisations(&mut self, realisations: i32, seed: u64) {
+        self.realisations = realisations.max(1);
+        self.substrate_seed = seed;
+    }
+
+    //seeds of the realisations of the substrate, the first draws of stream 0 of chacha12 from the substrate seed
+    fn substrate_seeds(&self) -> Vec<u64> {
+        let mut rng = Generator::new(Backend::ChaCha12, self.substrate_seed, 0);
+        return (0..self.realisations).map(|_| rng.gen()).collect();
+    }
+
+    //sets how many threads run the points and replicas of a batch at the same time
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    //saves every replica to a checkpoint in directory every interval steps, so an interrupted batch resumes
+    //where it stopped when it is run again and skips the replicas that were already complete
+    pub fn set_checkpoints(&mut self, directory: &str, interval: i32) -> Result<()> {
+        fs::create_dir_all(directory)?;
+        self.checkpoints = Some((directory.to_string(), interval.max(1)));
+        return Ok(());
+    }
+
+    //writes the timeseries of every replica of every point to directory in format, with its parameters in a json sidecar
+    pub fn set_output(&mut self, directory: &str, format: Format) -> Result<()> {
+        fs::create_dir_all(directory)?;
+        self.output = Some((directory.to_string(), format));
+        return Ok(());
+    }
+
+    //writes an ascii map of every replica to "{directory}/{title} {replica}" every interval sweeps and at the end of its run
+    pub fn set_snapshots(&mut self, directory: &str, interval: i32) -> Result<()> {
+        fs::create_dir_all(directory)?;
+        self.snapshots = Some((directory.to_string(), interval.max(1)));
+        return Ok(());
+    }
+
+    //the snapshot observer of the run called name, if snapshots are enabled. a directory that cannot be created is
+    //reported and the run goes on without snapshots
+    fn snapshots(&self, name: &str) -> Option<Snapshots> {
+        let (directory, interval) = self.snapshots.as_ref()?;
+        let path = format!("{}/{} {}", directory, self.title, name);
+        return report(&format!("could not write snapshots to {}", path), Snapshots::new(&path, *interval));
+    }
+
+    //sets the criteria that end the burn-in of every run, measuring starts only after it. no criteria turn the burn-in off
+    pub fn set_burnin(&mut self, criteria: Vec<Criterion>) -> Result<()> {
+        self.burnin = match criteria.is_empty() {
+            true => None,
+            false => Some(Equilibration::new(criteria)?),
+        };
+        return Ok(());
+    }
+
+    //equilibrates board before measuring, if burn-in criteria were set
+    fn burn_in(&self, board: &mut HexBoard) {
+        if let Some(burnin) = self.burnin.as_ref() {
+            let reason = burnin.clone().run(board);
+            println!("burn-in: {}", reason);
+        }
+    }
+
+    //runs every point of sweep and returns their records, one dimensional sweeps are also plotted against the swept value.
+    //failed checkpoint and timeseries writes are reported without stopping the runs, failed plots are returned
+    pub fn run_sweep(&self, sweep: &Sweep, sim_lenght: i32) -> Result<Vec<PointRecord>> {
+
+        println!("> Beginning sweep: {} on {} threads", self.title, self.threads);
+
+        let results = self.run_points(sweep, sim_lenght, true)?;
+        let labels: Vec<String> = results.iter().map(|result| sweep.label(&result.record.coordinates)).collect();
+        return self.plot_results(results, labels, sweep.axes.len() == 1, sim_lenght);
+
+    }
+
+    //runs the replicas of a single parameter point and returns its record without plotting it
+    pub fn run_point(&self, parameters: Parameters, sim_lenght: i32) -> Result<PointRecord> {
+        let sweep = Sweep::new(parameters, Parameter::Betaj, Values::List(vec![parameters.betaj]));
+        return Ok(self.run_points(&sweep, sim_lenght, false)?.remove(0).record);
+    }
+
+    //maps the phase diagram over fill and betaj, measuring |psi|, C and chi at every point and classifying its phase.
+    //the results are drawn as heatmaps over the (fill, betaj) plane with the phase boundaries
+    pub fn phase_diagram(&self, base: Parameters, fills: Values, betajs: Values, sim_lenght: i32) -> Result<Vec<(PointRecord, Phase)>> {
+
+        println!("> Beginning phase diagram: {} on {} threads", self.title, self.threads);
+
+        let sweep = Sweep::grid(base, (Parameter::Fill, fills), (Parameter::Betaj, betajs));
+        let fill_values = sweep.axes[0].1.clone();
+        let betaj_values = sweep.axes[1].1.clone();
+        let mut points: Vec<(PointRecord, Phase)> = vec![];
+        for result in self.run_points(&sweep, sim_lenght, false)? {
+            let phase = Phase::classify(&result.record);
+            println!("{}: {}", sweep.label(&result.record.coordinates), phase);
+            points.push((result.record, phase));
+        }
+
+        //the grid sweep varies betaj fastest, so point i is at fill i / betajs and betaj i % betajs
+        let grid = |value: fn(&PointRecord) -> f64| -> Vec<Vec<f64>> {
+            return points.chunks(betaj_values.len()).map(|row| row.iter().map(|p| value(&p.0)).collect()).collect();
+        };
+        let phases: Vec<Vec<usize>> = points.chunks(betaj_values.len()).map(|row| row.iter().map(|p| p.1.index()).collect()).collect();
+        let colors: Vec<Vec<RGBColor>> = points.chunks(betaj_values.len()).map(|row| row.iter().map(|p| p.1.color()).collect()).collect();
+        let plot = Plot::new(600, 500, 0..1, 0.0..1.0);
+        plot.plot_heatmap(&(self.title.to_owned() + " phases.svg"), &(self.title.to_owned() + " phases"), &fill_values, &betaj_values, colors, &phases)?;
+        for (name, values) in [
+            ("psi", grid(|r| r.psi.mean)),
+            ("specific heat", grid(|r| r.specific_heat.mean)),
+            ("susceptibility", grid(|r| r.susceptibility.mean)),
+        ] {
+            let colors: Vec<Vec<RGBColor>> = plot.get_colormap(&values, &WHITE, &RED);
+            plot.plot_heatmap(&format!("{} {}.svg", self.title, name), &format!("{} {}", self.title, name), &fill_values, &betaj_values, colors, &phases)?;
+        }
+        return Ok(points);
+
+    }
+
+    //runs every replica of every realisation of the substrate at every point of sweep, the sublattice order timeseries
+    //is kept only when keep_order is set. every replica runs on a stream of its own, so the results do not depend on
+    //the number of threads. replicas are numbered across realisations, replica r runs on realisation r / replicas.
+    //fails when the board of a point cannot be built
+    fn run_points(&self, sweep: &Sweep, sim_lenght: i32, keep_order: bool) -> Result<Vec<PointResult>> {
+        let points = sweep.points();
+        let substrate_seeds = self.substrate_seeds();
+        let mut jobs: Vec<(usize, i32, u64)> = vec![];
+        for point in 0..points.len() {
+            for replica in 0..self.replicas * self.realisations {
+                jobs.push((point, replica, substrate_seeds[(replica / self.replicas) as usize]));
             }
-            orders.push(order);
-            println!("fill percentage: {:.2}", fillpercentage[run as usize]);
         }
-        let mut labels: Vec<String> = Vec::new();
-        for value in fillpercentage {
-            labels.push(value.to_string());
+
+        let mut replicas = self.run_parallel(jobs.len(), |job| {
+            let (point, replica, substrate_seed) = jobs[job];
+            let name = format!("point {} replica {}", point, replica);
+            return self.measure_replica(Batchrun::stream(point, replica), substrate_seed, keep_order && replica == 0, sim_lenght, points[point].0, &name);
+        }).into_iter().collect::<Result<Vec<ReplicaResult>>>()?.into_iter();
+
+        let mut results: Vec<PointResult> = vec![];
+        for (parameters, coordinates) in points {
+            let point_replicas: Vec<ReplicaResult> = replicas.by_ref().take((self.replicas * self.realisations) as usize).collect();
+            println!("{}", sweep.label(&coordinates));
+            let result = self.combine_replicas(results.len(), parameters, coordinates, point_replicas);
+            if let Some(histograms) = result.histograms.as_ref() {
+                self.write_histograms(histograms, results.len(), &sweep.label(&result.record.coordinates), &result.record.generator());
+            }
+            results.push(result);
         }
-        order_plot.plot_multiple_orders(&(self.title.to_owned() + ".svg"), &self.title, orders, &BLUE, &RED, labels);
+        return Ok(results);
+    }
 
+    //runs job(0..count) on the configured number of threads and returns the results in job order
+    fn run_parallel<T: Send>(&self, count: usize, job: impl Fn(usize) -> T + Sync) -> Vec<T> {
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<T>>> = Mutex::new((0..count).map(|_| None).collect());
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(count.max(1)) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= count {
+                        break;
+                    }
+                    let result = job(index);
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+        return results.into_inner().unwrap().into_iter().map(|result| result.unwrap()).collect();
     }
 
-    pub fn betaj_test(&self, matrix_size: u16, sim_lenght: i32, min_betaj: f64, max_betaj: f64) {
+    //runs a single replica of a parameter point, the sublattice order timeseries is kept only when keep_order is set.
+    //when checkpoints are enabled the replica resumes from its last checkpoint, and completed replicas are not run again.
+    //the replica draws from stream of the seed of the batch and the substrate of parameters is drawn from substrate_seed
+    fn measure_replica(&self, stream: u64, substrate_seed: u64, keep_order: bool, sim_lenght: i32, parameters: Parameters, name: &str) -> Result<ReplicaResult> {
+        let path = self.checkpoints.as_ref().map(|(directory, _)| format!("{}/{} {}.ckpt", directory, self.title, name));
+        let mut state = match path.as_ref().and_then(|path| self.load_state(path, stream, substrate_seed, sim_lenght, &parameters)) {
+            Some(state) => state,
+            None => {
+                let mut board = parameters.build(Generator::new(self.backend, self.seed, stream))?;
+                board.set_substrate(parameters.substrate, substrate_seed);
+                board.initialize_state(parameters.initial);
+                self.burn_in(&mut board);
+                ReplicaState::new(board)
+            }
+        };
+        if state.step >= sim_lenght {
+            println!("{}: already complete, skipping", name);
+        }
+        else if state.step > 0 {
+            println!("{}: resuming from step {} of {}", name, state.step, sim_lenght);
+        }
 
-        println!("> Beginning betaj test");
+        //the sublattice orders are always recorded when the timeseries are written out or histogrammed
+        state.samples.keep_order = keep_order || self.output.is_some() || self.histograms.is_some();
+        let mut snapshots = self.snapshots(name);
+        while state.step < sim_lenght {
+            state.board.advance_timestep_repulsive();
+            observer::observe(&mut [&mut state.samples], &state.board, state.step);
+            if let Some(snapshots) = snapshots.as_mut() {
+                observer::observe(&mut [snapshots], &state.board, state.step);
+            }
+            state.step += 1;
+            if let (Some(path), Some((_, interval))) = (path.as_ref(), self.checkpoints.as_ref()) {
+                if state.step % interval == 0 || state.step == sim_lenght {
+                    self.save_state(path, sim_lenght, parameters.initial, &state);
+                }
+            }
+        }
+        let mut observers: Vec<&mut dyn Observer> = vec![&mut state.samples];
+        if let Some(snapshots) = snapshots.as_mut() {
+            observers.push(snapshots);
+        }
+        observer::finish(&mut observers, &state.board);
 
-        let mut rng: StdRng = StdRng::seed_from_u64(3463462432);
-        let order_plot = Plot::new(500, 500, 0..sim_lenght, 0.0..1.0);
-        let mut orders: Vec<Vec<Vec<(i32, f64)>>> = vec![];
-        let betajvalues = self.gen_range(self.runs_number as f64, min_betaj, max_betaj);
-
-        for run in 0..self.runs_number {
-            
-            let mut board = HexBoard::new(
-                matrix_size,
-                StdRng::seed_from_u64(rng.gen()),
-                betajvalues[run as usize],
-                1.0/3.0,
-            );
-            let mut order: Vec<Vec<(i32, f64)>> = vec![vec![], vec![], vec![]];
-            println!("betaj: {:.2}", betajvalues[run as usize]);
-            board.initialize();
+        if let Some((directory, format)) = self.output.as_ref() {
+            let stem = format!("{}/{} {}", directory, self.title, name);
+            report(&format!("could not write timeseries {}", stem), self.series(&state, sim_lenght, &parameters).write(&stem, *format));
+        }
 
-            for x in 0..sim_lenght {
-                board.advance_timestep_repulsive();
-                let run_orders = board.get_order();
-                order[0].push((x, run_orders[0] as f64));
-                order[1].push((x, run_orders[1] as f64));
-                order[2].push((x, run_orders[2] as f64));
+        let samples = state.samples;
+        let psi_values: Vec<f64> = samples.psi.iter().map(|p| p.1).collect();
+        let tau = self.report_sampling(&samples.energy, &psi_values);
+        let count = samples.psi.len() as f64;
+        //chi = beta N var(|psi|), with N the sites of the lattice like the specific heat
+        let sites = state.board.lattice.site_count(state.board.size);
+        let specific_heat = specific_heat(self.error_method, &samples.hamiltonian, parameters.betaj, sites);
+        let susceptibility = self.error_method.estimate_with(&psi_values, statistics::variance).scale(parameters.betaj * sites as f64);
+        //built from the stored samples, so a resumed replica counts the samples from before its checkpoint too
+        let histograms = self.histograms.map(|energy_width| {
+            let mut histograms = Histograms::new(energy_width, 1);
+            for (sample, (&energy, &psi)) in samples.energy.iter().zip(psi_values.iter()).enumerate() {
+                let orders: Vec<f64> = samples.order.iter().filter_map(|series| series.get(sample).map(|o| o.1)).collect();
+                histograms.record(energy, psi, &orders);
             }
-            orders.push(order);
+            return histograms;
+        });
+        return Ok(ReplicaResult{
+            order: samples.order.iter().map(|series| statistics::thin(series, tau)).collect(),
+            psi_estimate: self.error_method.estimate(&psi_values),
+            energy_estimate: self.error_method.estimate(&samples.energy),
+            specific_heat,
+            susceptibility,
+            sublattices: samples.sublattices.into_iter().map(|total| total / count).collect(),
+            psi: samples.psi,
+            acceptance_rate: state.board.acceptance_rate(),
+            histograms,
+        });
+    }
+
+    //the observables sampled once per sweep by a replica, with the parameters, seed and stream that produced them
+    fn series(&self, state: &ReplicaState, sim_lenght: i32, parameters: &Parameters) -> Timeseries {
+        let mut columns = vec!["energy".to_string(), "psi".to_string()];
+        columns.extend((1..=state.samples.order.len()).map(|sublattice| format!("order {}", sublattice)));
+        let mut series = Timeseries::new(&columns.iter().map(|column| column.as_str()).collect::<Vec<&str>>());
+        series.set("title", &self.title);
+        series.set_parameters(parameters);
+        series.set_generator(&state.board.rng);
+        if let Some(realisation) = state.board.substrate.as_ref() {
+            series.set("substrate seed", realisation.seed);
         }
-        let mut labels: Vec<String> = Vec::new();
-        for value in betajvalues {
-            labels.push(value.to_string());
+        series.set("length", sim_lenght);
+        let sites = state.board.size as i64 * state.board.size as i64;
+        for (sample, (&energy, &(sweep, psi))) in state.samples.energy.iter().zip(state.samples.psi.iter()).enumerate() {
+            let mut values = vec![energy, psi];
+            values.extend(state.samples.order.iter().map(|order| order.get(sample).map_or(f64::NAN, |o| o.1)));
+            series.push(sweep as i64 * sites, values);
+        }
+        return series;
+    }
+
+    //writes the replica state to its checkpoint with the initial state it was started from, a failed write is reported
+    //without stopping the batch
+    fn save_state(&self, path: &str, sim_lenght: i32, initial: InitialState, state: &ReplicaState) {
+        let mut writer = Writer::new();
+        writer.put_u64(state.board.rng.stream());
+        writer.put_u32(sim_lenght as u32);
+        writer.put_u32(state.step as u32);
+        checkpoint::write_initial(&mut writer, initial);
+        checkpoint::write_board(&mut writer, &state.board);
+        for series in &state.samples.order {
+            writer.put_series(series);
+        }
+        writer.put_f64s(&state.samples.energy);
+        writer.put_series(&state.samples.psi);
+        writer.put_f64s(&state.samples.sublattices);
+        writer.put_f64s(&state.samples.hamiltonian);
+        report(&format!("could not write checkpoint {}", path), writer.save(path));
+    }
+
+    //reads the replica state from its checkpoint, if it exists and was written for the same generator, seeds, length and
+    //every one of the parameters
+    fn load_state(&self, path: &str, stream: u64, substrate_seed: u64, sim_lenght: i32, parameters: &Parameters) -> Option<ReplicaState> {
+        let mut reader = Reader::open(path).ok()?;
+        if reader.get_u64().ok()? != stream || reader.get_u32().ok()? != sim_lenght as u32 {
+            return None;
+        }
+        let step = reader.get_u32().ok()? as i32;
+        let initial = checkpoint::read_initial(&mut reader).ok()?;
+        let board = checkpoint::read_board(&mut reader).ok()?;
+        let same_parameters = board.rng.backend() == self.backend
+            && board.rng.seed() == self.seed
+            && board.rng.stream() == stream
+            && board.size == parameters.size
+            && board.fillrate == parameters.fill as f32
+            && board.disorder == parameters.betaj
+            && board.coupling == parameters.coupling
+            && board.field == parameters.field
+            && board.boundary == parameters.boundary
+            && initial == parameters.initial
+            && board.lattice.name() == parameters.lattice.name()
+            && board.superstructure == parameters.superstructure
+            && board.storage() == parameters.storage
+            && match board.substrate.as_ref() {
+                None => parameters.substrate.is_clean(),
+                Some(realisation) => realisation.substrate == parameters.substrate && realisation.seed == substrate_seed,
+            };
+        if !same_parameters {
+            return None;
+        }
+        let mut state = ReplicaState::new(board);
+        state.step = step;
+        for series in state.samples.order.iter_mut() {
+            *series = reader.get_series().ok()?;
         }
-        order_plot.plot_multiple_orders(&(self.title.to_owned() + ".svg"), &self.title, orders, &BLUE, &RED, labels);
+        state.samples.energy = reader.get_f64s().ok()?;
+        state.samples.psi = reader.get_series().ok()?;
+        state.samples.sublattices = reader.get_f64s().ok()?;
+        state.samples.hamiltonian = reader.get_f64s().ok()?;
+        return Some(state);
+    }
 
+    //averages the replicas of a parameter point, so over every realisation of the substrate, the order timeseries is taken from the first replica
+    fn combine_replicas(&self, point: usize, parameters: Parameters, coordinates: Vec<f64>, replicas: Vec<ReplicaResult>) -> PointResult {
+        let count = replicas.len() as f64;
+        let mut mean_order: Vec<f64> = vec![0.0; replicas.first().map_or(0, |replica| replica.sublattices.len())];
+        for replica in &replicas {
+            for (total, value) in mean_order.iter_mut().zip(replica.sublattices.iter()) {
+                *total += value / count;
+            }
+        }
+        let psi_estimates: Vec<Estimate> = replicas.iter().map(|r| r.psi_estimate).collect();
+        let energy_estimates: Vec<Estimate> = replicas.iter().map(|r| r.energy_estimate).collect();
+        let record = PointRecord{
+            parameters,
+            coordinates,
+            psi: Estimate::combine(&psi_estimates),
+            energy: Estimate::combine(&energy_estimates),
+            specific_heat: Estimate::combine(&replicas.iter().map(|r| r.specific_heat).collect::<Vec<Estimate>>()),
+            susceptibility: Estimate::combine(&replicas.iter().map(|r| r.susceptibility).collect::<Vec<Estimate>>()),
+            order: mean_order,
+            acceptance_rate: replicas.iter().map(|r| r.acceptance_rate).sum::<f64>() / count,
+            backend: self.backend,
+            seed: self.seed,
+            streams: Batchrun::stream(point, 0)..Batchrun::stream(point, replicas.len() as i32),
+        };
+        println!("{} over {} replicas", record, replicas.len());
+        let psi_series: Vec<Vec<(i32, f64)>> = replicas.iter().map(|r| r.psi.clone()).collect();
+        let mut histograms: Option<Histograms> = None;
+        for replica_histograms in replicas.iter().filter_map(|r| r.histograms.as_ref()) {
+            match histograms.as_mut() {
+                Some(histograms) => histograms.merge(replica_histograms),
+                None => histograms = Some(replica_histograms.clone()),
+            }
+        }
+        let order = replicas.into_iter().next().map(|r| r.order).unwrap_or_default();
+        return PointResult{record, order, psi_band: statistics::replica_band(&psi_series), histograms};
     }
 
-    pub fn size_test(&self, min_matrix_size: u8, max_matrix_size: u8, sim_lenght: i32, betaj: f64) {
+    //writes the histograms of point to csv and draws them, a failed write is reported without stopping the batch
+    fn write_histograms(&self, histograms: &Histograms, point: usize, label: &str, generator: &str) {
+        let stem = format!("{} point {}", self.title, point);
+        report(&format!("could not write histograms of {}", stem), histograms.write_csv(&format!("{} histograms.csv", stem), generator));
+        report(&format!("could not plot histograms of {}", stem), histograms.plot(&stem, &format!("{} {}", self.title, label)));
+    }
 
-        println!("> Beginning size test");
+    //plots the sublattice orders of the first replica and the |psi| bands over the replicas of every point,
+    //and |psi| against the swept value when only one parameter varies
+    fn plot_results(&self, results: Vec<PointResult>, labels: Vec<String>, one_dimensional: bool, sim_lenght: i32) -> Result<Vec<PointRecord>> {
+        let mut orders: Vec<Vec<Vec<(i32, f64)>>> = vec![];
+        let mut bands: Vec<Vec<(i32, f64, f64)>> = vec![];
+        let mut records: Vec<PointRecord> = vec![];
+        for result in results {
+            orders.push(result.order);
+            bands.push(result.psi_band);
+            records.push(result.record);
+        }
 
-        let mut rng: StdRng = StdRng::seed_from_u64(3463462432);
         let order_plot = Plot::new(500, 500, 0..sim_lenght, 0.0..1.0);
-        let mut orders: Vec<Vec<Vec<(i32, f64)>>> = vec![];
-        let sizes = self.gen_range(self.runs_number as f64, min_matrix_size as f64, max_matrix_size as f64);
-
-        for run in 0..self.runs_number {
-            
-            let mut board = HexBoard::new(
-                sizes[run as usize] as u16,
-                StdRng::seed_from_u64(rng.gen()),
-                betaj,
-                1.0/3.0,
-            );
-            let mut order: Vec<Vec<(i32, f64)>> = vec![vec![], vec![], vec![]];
-            println!("size: {}", sizes[run as usize]);
-            board.initialize();
+        order_plot.plot_multiple_orders(&(self.title.to_owned() + ".svg"), &self.title, orders, &BLUE, &RED, labels.clone())?;
+
+        let sweeps = bands.iter().map(|band| band.len()).max().unwrap_or(1) as i32;
+        let band_plot = Plot::new(500, 500, 0..sweeps, 0.0..1.0);
+        band_plot.plot_confidence_bands(&(self.title.to_owned() + " psi.svg"), &self.title, bands, &BLUE, &RED, labels)?;
+
+        if one_dimensional {
+            let points: Vec<(f64, f64, f64)> = records.iter().map(|r| (r.coordinates[0], r.psi.mean, r.psi.error)).collect();
+            let min = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+            let max = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+            let margin = ((max - min) * 0.1).max(0.1);
+            let summary_plot = Plot::new(500, 500, 0..1, 0.0..1.0);
+            summary_plot.plot_errorbars(&(self.title.to_owned() + " summary.svg"), &self.title, (min - margin)..(max + margin), points, &BLUE, "|psi|")?;
+        }
+        return Ok(records);
+    }
+
+    //prints the autocorrelation time and effective sample size of energy and |psi| sampled once per sweep,
+    //returns the larger autocorrelation time in sweeps
+    fn report_sampling(&self, energy: &[f64], psi: &[f64]) -> f64 {
+        let energy_sampling = Sampling::new(energy);
+        let psi_sampling = Sampling::new(psi);
+        println!(
+            "energy: {:.2} (tau {:.1} sweeps, {:.0} effective samples), |psi|: {:.3} (tau {:.1} sweeps, {:.0} effective samples)",
+            energy_sampling.mean(), energy_sampling.tau, energy_sampling.effective_samples,
+            psi_sampling.mean(), psi_sampling.tau, psi_sampling.effective_samples,
+        );
+        return energy_sampling.tau.max(psi_sampling.tau);
+    }
 
+    //tracks every particle at every point of sweep, to measure surface diffusion, on the first realisation of the substrate
+    pub fn diffusion_test(&self, sweep: &Sweep, sim_lenght: i32) -> Result<()> {
+
+        println!("> Beginning diffusion test on {} threads", self.threads);
+
+        let points = sweep.points();
+        let substrate_seed = self.substrate_seeds()[0];
+
+        //every point runs on the stream of its first replica
+        let runs = self.run_parallel(points.len(), |point| {
+            let mut board = points[point].0.build(Generator::new(self.backend, self.seed, Batchrun::stream(point, 0)))?;
+            let sweep_lenght = board.size as i32 * board.size as i32;
+            let mut msd = observer::msd(1);
+            let mut snapshots = self.snapshots(&format!("point {}", point));
+
+            board.set_substrate(points[point].0.substrate, substrate_seed);
+            board.initialize_state(points[point].0.initial);
+            self.burn_in(&mut board);
+            board.enable_tracking();
+
+            let mut observers: Vec<&mut dyn Observer> = vec![&mut msd];
+            if let Some(snapshots) = snapshots.as_mut() {
+                observers.push(snapshots);
+            }
             for x in 0..sim_lenght {
                 board.advance_timestep_repulsive();
-                let run_orders = board.get_order();
-                order[0].push((x, run_orders[0] as f64));
-                order[1].push((x, run_orders[1] as f64));
-                order[2].push((x, run_orders[2] as f64));
+                observer::observe(&mut observers, &board, x);
             }
-            orders.push(order);
-        }
+            observer::finish(&mut observers, &board);
+            let msd: Vec<(i32, f64)> = msd.first().iter().map(|&(x, value)| (x / sweep_lenght, value)).collect();
+            return Ok((msd, board.acceptance_rate()));
+        }).into_iter().collect::<Result<Vec<_>>>()?;
+
+        let mut msds: Vec<Vec<(i32, f64)>> = vec![];
         let mut labels: Vec<String> = Vec::new();
-        for value in sizes {
-            labels.push(value.to_string());
+        for ((_, coordinates), (msd, acceptance_rate)) in points.iter().zip(runs) {
+            println!(
+                "{}: diffusion coefficient: {:.4}, acceptance rate: {:.4}",
+                sweep.label(coordinates),
+                tracking::diffusion_coefficient(&msd),
+                acceptance_rate,
+            );
+            labels.push(sweep.label(coordinates));
+            msds.push(msd);
         }
-        order_plot.plot_multiple_orders(&(self.title.to_owned() + ".svg"), &self.title, orders, &BLUE, &RED, labels);
+        let max_sweeps = msds.iter().map(|msd| msd.len()).max().unwrap_or(1) as i32;
+        let max_msd = msds.iter().flatten().fold(1.0, |max: f64, value| max.max(value.1));
+        let msd_plot = Plot::new(500, 500, 0..max_sweeps, 0.0..max_msd);
+        msd_plot.plot_multiple_timeseries(&(self.title.to_owned() + ".svg"), &self.title, msds, &BLUE, &RED, labels)?;
+        return Ok(());
+
+    }
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Storage;
+
+    #[test]
+    fn replicas_finish_their_observers() {
+        let directory = std::env::temp_dir().join(format!("tesina_mfs_snapshots_{}", std::process::id()));
+        let mut batch = Batchrun::new("finish");
+        batch.set_replicas(2, ErrorMethod::Blocking);
+        batch.set_snapshots(directory.to_str().unwrap(), 1000).unwrap();
+        batch.run_point(Parameters::new(6, 1.0 / 3.0, 1.0), 36 * 20).unwrap();
+        let finals: Vec<bool> = (0..2).map(|replica| directory.join(format!("finish point 0 replica {}/final.map", replica)).exists()).collect();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(finals, vec![true, true]);
     }
 
-    //function to generate a range of values with equal distance
-    pub fn gen_range(&self, step_number: f64, min: f64, max: f64) -> Vec<f64> {
-        let step = (max - min) / step_number;
-        let mut steps = Vec::with_capacity(step_number as usize);
-        for i in 0..step_number as i32 {
-            steps.push(min + i as f64 * step);
+    #[test]
+    fn histograms_need_a_positive_width() {
+        let mut batch = Batchrun::new("histograms");
+        for width in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(batch.set_histograms(width).is_err(), "width {}", width);
         }
-        return steps;
+        assert!(batch.histograms.is_none());
+        assert!(batch.set_histograms(4.0).is_ok());
+    }
+
+    #[test]
+    fn checkpoints_only_resume_the_same_parameters() {
+        let path = std::env::temp_dir().join(format!("tesina_mfs_replica_{}.ckpt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let batch = Batchrun::new("resume");
+        let parameters = Parameters::new(6, 1.0 / 3.0, 1.0);
+        let stream = Batchrun::stream(0, 0);
+        let mut board = parameters.build(Generator::new(batch.backend, batch.seed, stream)).unwrap();
+        board.initialize_state(parameters.initial);
+        batch.save_state(path, 100, parameters.initial, &ReplicaState::new(board));
+        let resumes = |parameters: Parameters| batch.load_state(path, stream, 0, 100, &parameters).is_some();
+        let resumed = [resumes(parameters), resumes(parameters.starting_from(InitialState::Slab)), resumes(parameters.stored_as(Storage::Packed))];
+        fs::remove_file(path).unwrap();
+        assert_eq!(resumed, [true, false, false]);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,143 @@
+use crate::HexBoard;
+use crate::batchrun::Batchrun;
+use crate::sweep::Parameters;
+use crate::parallel::Decomposition;
+use crate::error::Result;
+use crate::grid::Storage;
+use crate::random::{Backend, Generator};
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::process::Command;
+use std::thread;
+use std::time::Instant;
+
+const SIZES: [u16; 3] = [12, 30, 60];
+const FILLS: [f64; 3] = [0.1, 1.0 / 3.0, 0.6];
+//shortest time a measurement is repeated for, the best of three repetitions is kept
+const MIN_SECONDS: f64 = 0.2;
+//sweeps of the batch point, long enough for the point to be dominated by the dynamics
+const POINT_SWEEPS: i32 = 200;
+
+//one measurement of a kernel on a board of size at fill
+pub struct Benchmark {
+    pub name: &'static str,
+    pub size: u16,
+    pub fill: f64,
+    pub value: f64,
+    pub unit: &'static str,
+}
+
+//runs every benchmark, prints it next to the last run of another commit found in path, and appends the results to path
+//as csv rows of commit, profile, benchmark, size, fill, value, unit. debug and release builds are kept apart
+pub fn run(path: &str) -> Result<Vec<Benchmark>> {
+    let commit = commit();
+    let profile = if cfg!(debug_assertions) { "debug" } else { "release" };
+    let previous = previous(path, &commit, profile);
+    println!("> Benchmarks of {} ({} build)", commit, profile);
+
+    let mut benchmarks = vec![];
+    for size in SIZES {
+        for fill in FILLS {
+            benchmarks.extend(kernels(size, fill, Storage::Cells));
+            benchmarks.extend(kernels(size, fill, Storage::Packed));
+            benchmarks.push(point(size, fill)?);
+        }
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "commit,profile,benchmark,size,fill,value,unit")?;
+    }
+    for benchmark in &benchmarks {
+        let key = (benchmark.name.to_string(), benchmark.size, format!("{:.4}", benchmark.fill));
+        let change = match previous.get(&key) {
+            Some(old) => format!(" ({:+.1}% against {})", 100.0 * (benchmark.value / old.1 - 1.0), old.0),
+            None => String::new(),
+        };
+        println!("{} on {}x{} at fill {:.3}: {:.4} {}{}", benchmark.name, benchmark.size, benchmark.size, benchmark.fill, benchmark.value, benchmark.unit, change);
+        writeln!(file, "{},{},{},{},{:.4},{},{}", commit, profile, benchmark.name, benchmark.size, benchmark.fill, benchmark.value, benchmark.unit)?;
+    }
+    return Ok(benchmarks);
+}
+
+//the serial and parallel move rates and the cost of the measurements on a board equilibrated for a few sweeps, with the cells
+//kept in storage. the names of the benchmarks of bit-packed boards end in (packed)
+fn kernels(size: u16, fill: f64, storage: Storage) -> Vec<Benchmark> {
+    let mut board = HexBoard::with_storage(size, Generator::new(Backend::ChaCha12, 2718281828, 0), 3.0, fill as f32, storage);
+    board.initialize();
+    let sweep = size as usize * size as usize;
+    for _ in 0..10 * sweep {
+        board.advance_timestep_repulsive();
+    }
+    let moves = time(|| {
+        for _ in 0..sweep {
+            board.advance_timestep_repulsive();
+        }
+    }) / sweep as f64;
+    //the same moves in parallel sweeps on every thread, with blocks about 6 cells wide
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let decomposition = Decomposition::new((size / 6).max(2) / 2 * 2, threads);
+    let parallel_moves = time(|| decomposition.sweep(&mut board)) / sweep as f64;
+    let mut energy = 0.0;
+    let total_energy = time(|| energy += board.total_energy());
+    let mut order = 0.0;
+    let get_order = time(|| order += board.get_order()[0]);
+    //keeps the measurements from being optimized away
+    assert!(energy.is_finite() && order.is_finite());
+    let name = |cells: &'static str, packed: &'static str| if storage == Storage::Packed { packed } else { cells };
+    return vec![
+        Benchmark{name: name("move attempts", "move attempts (packed)"), size, fill, value: 1.0 / moves / 1e6, unit: "million per second"},
+        Benchmark{name: name("parallel move attempts", "parallel move attempts (packed)"), size, fill, value: 1.0 / parallel_moves / 1e6, unit: "million per second"},
+        Benchmark{name: name("total_energy", "total_energy (packed)"), size, fill, value: total_energy * 1e6, unit: "microseconds"},
+        Benchmark{name: name("get_order", "get_order (packed)"), size, fill, value: get_order * 1e6, unit: "microseconds"},
+    ];
+}
+
+//a single replica of a batch point on one thread, without burn-in, measuring for POINT_SWEEPS sweeps
+fn point(size: u16, fill: f64) -> Result<Benchmark> {
+    let batchrun = Batchrun::new("Benchmark");
+    let start = Instant::now();
+    batchrun.run_point(Parameters::new(size, fill, 3.0), POINT_SWEEPS * size as i32 * size as i32)?;
+    return Ok(Benchmark{name: "batch point", size, fill, value: 1.0 / start.elapsed().as_secs_f64(), unit: "points per second"});
+}
+
+//seconds per call of work, repeating it until MIN_SECONDS have passed and keeping the fastest of three repetitions
+fn time(mut work: impl FnMut()) -> f64 {
+    let mut best = f64::INFINITY;
+    for _ in 0..3 {
+        let start = Instant::now();
+        let mut calls = 0;
+        while start.elapsed().as_secs_f64() < MIN_SECONDS {
+            work();
+            calls += 1;
+        }
+        best = best.min(start.elapsed().as_secs_f64() / calls as f64);
+    }
+    return best;
+}
+
+//the checked out commit, marked when the tree has uncommitted changes
+fn commit() -> String {
+    let git = |args: &[&str]| Command::new("git").args(args).output().ok().filter(|output| output.status.success()).map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    return match git(&["rev-parse", "--short", "HEAD"]) {
+        Some(hash) if git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|status| !status.is_empty()) => hash + "-dirty",
+        Some(hash) => hash,
+        None => "unknown".to_string(),
+    };
+}
+
+//the results of the last commit other than this one that was benchmarked with the same profile, by benchmark, size and fill
+fn previous(path: &str, commit: &str, profile: &str) -> HashMap<(String, u16, String), (String, f64)> {
+    let mut results = HashMap::new();
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    let rows: Vec<Vec<&str>> = contents.lines().skip(1).map(|line| line.split(',').collect()).filter(|row: &Vec<&str>| row.len() == 7).collect();
+    let last = rows.iter().rev().find(|row| row[0] != commit && row[1] == profile).map(|row| row[0]);
+    for row in rows.iter().filter(|row| Some(row[0]) == last && row[1] == profile) {
+        if let (Ok(size), Ok(value)) = (row[3].parse(), row[5].parse()) {
+            results.insert((row[2].to_string(), size, row[4].to_string()), (row[0].to_string(), value));
+        }
+    }
+    return results;
+}
@@ -0,0 +1,350 @@
+use crate::HexBoard;
+use crate::hexboard::{Boundary, InitialState};
+use crate::substrate::{SiteEnergies, Substrate};
+use crate::lattice;
+use crate::superstructure::Superstructure;
+use crate::grid::Storage;
+use crate::random::{Backend, Generator};
+use crate::tracking::Tracker;
+
+use std::fs::{self, File};
+use std::io::{self, prelude::*};
+
+//checkpoints start with this tag followed by the format version
+const MAGIC: &[u8; 8] = b"HEXBOARD";
+pub const VERSION: u32 = 1;
+
+//little endian binary encoder for checkpoints
+pub struct Writer {
+    pub bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        let mut writer = Writer{bytes: vec![]};
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.put_u32(VERSION);
+        return writer;
+    }
+
+    pub fn put_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn put_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn put_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn put_f64(&mut self, value: f64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn put_str(&mut self, value: &str) {
+        self.put_u64(value.len() as u64);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    pub fn put_f64s(&mut self, values: &[f64]) {
+        self.put_u64(values.len() as u64);
+        for &value in values {
+            self.put_f64(value);
+        }
+    }
+
+    pub fn put_series(&mut self, series: &[(i32, f64)]) {
+        self.put_u64(series.len() as u64);
+        for &(x, value) in series {
+            self.put_u32(x as u32);
+            self.put_f64(value);
+        }
+    }
+
+    //writes the bytes to path through a temporary file, so an interrupted write never leaves a broken checkpoint
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let temporary = format!("{}.tmp", path);
+        let mut file = File::create(&temporary)?;
+        file.write_all(&self.bytes)?;
+        file.flush()?;
+        return fs::rename(temporary, path);
+    }
+}
+
+//decoder for the bytes written by Writer
+pub struct Reader {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl Reader {
+    //reads a checkpoint and checks its tag and version
+    pub fn open(path: &str) -> io::Result<Self> {
+        let mut reader = Reader{bytes: fs::read(path)?, position: 0};
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a checkpoint"));
+        }
+        let version = reader.get_u32()?;
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported checkpoint version {}", version)));
+        }
+        return Ok(reader);
+    }
+
+    fn take(&mut self, count: usize) -> io::Result<&[u8]> {
+        if self.position + count > self.bytes.len() {
+            return Err(invalid("truncated checkpoint"));
+        }
+        self.position += count;
+        return Ok(&self.bytes[self.position - count..self.position]);
+    }
+
+    pub fn get_u8(&mut self) -> io::Result<u8> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    pub fn get_u32(&mut self) -> io::Result<u32> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    pub fn get_u64(&mut self) -> io::Result<u64> {
+        return Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+    }
+
+    pub fn get_f64(&mut self) -> io::Result<f64> {
+        return Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+    }
+
+    pub fn get_str(&mut self) -> io::Result<String> {
+        let length = self.get_u64()? as usize;
+        return String::from_utf8(self.take(length)?.to_vec()).map_err(|_| invalid("invalid string"));
+    }
+
+    pub fn get_f64s(&mut self) -> io::Result<Vec<f64>> {
+        let length = self.get_u64()?;
+        return (0..length).map(|_| self.get_f64()).collect();
+    }
+
+    pub fn get_series(&mut self) -> io::Result<Vec<(i32, f64)>> {
+        let length = self.get_u64()?;
+        return (0..length).map(|_| Ok((self.get_u32()? as i32, self.get_f64()?))).collect();
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+//encodes the full state of board: parameters, counters, random generator, occupancy, particle tracking, substrate, lattice
+//and superstructure.
+//every cell is 0 when empty and 1 + species when occupied
+pub fn write_board(writer: &mut Writer, board: &HexBoard) {
+    writer.put_u32(board.size as u32);
+    writer.put_f64(board.fillrate as f64);
+    writer.put_f64(board.disorder);
+    writer.put_f64(board.coupling);
+    writer.put_f64(board.field);
+    writer.put_u8(if board.boundary == Boundary::Open { 1 } else { 0 });
+    writer.put_u64(board.steps);
+    writer.put_u64(board.attempted_jumps);
+    writer.put_u64(board.accepted_jumps);
+
+    write_generator(writer, &board.rng);
+
+    for x in 0..board.size as usize {
+        for y in 0..board.size as usize {
+            let hex = board.get_cell(x as i16, y as i16);
+            writer.put_u8(if hex.value { 1 + hex.species } else { 0 });
+        }
+    }
+
+    match &board.tracker {
+        None => writer.put_u8(0),
+        Some(tracker) => {
+            writer.put_u8(1);
+            let starts: Vec<f64> = tracker.start.iter().flat_map(|p| [p.0, p.1]).collect();
+            let positions: Vec<f64> = tracker.positions.iter().flat_map(|p| [p.0, p.1]).collect();
+            writer.put_f64s(&starts);
+            writer.put_f64s(&positions);
+            for column in &tracker.ids {
+                for id in column {
+                    writer.put_u64(id.map(|id| id as u64).unwrap_or(u64::MAX));
+                }
+            }
+        }
+    }
+
+    writer.put_u64(board.species_count() as u64);
+    for row in &board.interactions {
+        for &interaction in row {
+            writer.put_f64(interaction);
+        }
+    }
+    writer.put_f64s(&board.composition);
+    writer.put_f64(board.swap_probability);
+    writer.put_u64(board.attempted_swaps);
+    writer.put_u64(board.accepted_swaps);
+
+    match &board.substrate {
+        None => writer.put_u8(0),
+        Some(realisation) => {
+            writer.put_u8(1);
+            let substrate = realisation.substrate;
+            writer.put_f64(substrate.blocked);
+            writer.put_f64(substrate.impurities);
+            let (kind, spread) = match substrate.site_energies {
+                SiteEnergies::Flat => (0, 0.0),
+                SiteEnergies::Uniform(width) => (1, width),
+                SiteEnergies::Gaussian(sigma) => (2, sigma),
+            };
+            writer.put_u8(kind);
+            writer.put_f64(spread);
+            writer.put_u64(realisation.seed);
+        }
+    }
+    writer.put_str(board.lattice.name());
+    writer.put_str(board.superstructure.map_or("", |superstructure| superstructure.name()));
+    writer.put_str(board.storage().name());
+}
+
+//encodes a random generator: its backend, the seed it was built from, its stream and its position along the stream
+fn write_generator(writer: &mut Writer, rng: &Generator) {
+    writer.put_str(rng.backend().name());
+    writer.put_u64(rng.seed());
+    writer.put_u64(rng.stream());
+    writer.bytes.extend_from_slice(&rng.position().to_le_bytes());
+}
+
+//rebuilds a random generator encoded by write_generator
+fn read_generator(reader: &mut Reader) -> io::Result<Generator> {
+    let name = reader.get_str()?;
+    let backend = Backend::by_name(&name).ok_or_else(|| invalid(&format!("unknown random generator {}", name)))?;
+    let seed = reader.get_u64()?;
+    let stream = reader.get_u64()?;
+    let position = u128::from_le_bytes(reader.take(16)?.try_into().unwrap());
+    return Ok(Generator::at(backend, seed, stream, position));
+}
+
+//encodes the initial state a board was started from, which the board itself does not keep
+pub fn write_initial(writer: &mut Writer, initial: InitialState) {
+    let (kind, value) = match initial {
+        InitialState::Random => (0, 0.0),
+        InitialState::Ordered(sublattice) => (1, sublattice as f64),
+        InitialState::Domains => (2, 0.0),
+        InitialState::Slab => (3, 0.0),
+        InitialState::Nucleus(radius) => (4, radius),
+    };
+    writer.put_u8(kind);
+    writer.put_f64(value);
+}
+
+//decodes an initial state encoded by write_initial
+pub fn read_initial(reader: &mut Reader) -> io::Result<InitialState> {
+    let kind = reader.get_u8()?;
+    let value = reader.get_f64()?;
+    return match kind {
+        0 => Ok(InitialState::Random),
+        1 => Ok(InitialState::Ordered(value as u8)),
+        2 => Ok(InitialState::Domains),
+        3 => Ok(InitialState::Slab),
+        4 => Ok(InitialState::Nucleus(value)),
+        _ => Err(invalid(&format!("unknown initial state {}", kind))),
+    };
+}
+
+//rebuilds a board encoded by write_board
+pub fn read_board(reader: &mut Reader) -> io::Result<HexBoard> {
+    let size = reader.get_u32()? as u16;
+    let fillrate = reader.get_f64()? as f32;
+    let disorder = reader.get_f64()?;
+    let coupling = reader.get_f64()?;
+    let field = reader.get_f64()?;
+    let boundary = if reader.get_u8()? == 1 { Boundary::Open } else { Boundary::Periodic };
+    let steps = reader.get_u64()?;
+    let attempted_jumps = reader.get_u64()?;
+    let accepted_jumps = reader.get_u64()?;
+    let rng = read_generator(reader)?;
+
+    let mut board = HexBoard::new(size, rng, disorder, fillrate);
+    board.set_coupling(coupling);
+    board.set_field(field);
+    board.set_boundary(boundary);
+    board.steps = steps;
+    board.attempted_jumps = attempted_jumps;
+    board.accepted_jumps = accepted_jumps;
+    for x in 0..size as i16 {
+        for y in 0..size as i16 {
+            let cell = reader.get_u8()?;
+            board.set_cell(x, y, cell > 0);
+            board.set_cell_species(x, y, cell.saturating_sub(1));
+        }
+    }
+
+    if reader.get_u8()? == 1 {
+        let mut tracker = Tracker::new(&board);
+        let pairs = |values: Vec<f64>| values.chunks_exact(2).map(|p| (p[0], p[1])).collect::<Vec<(f64, f64)>>();
+        tracker.start = pairs(reader.get_f64s()?);
+        tracker.positions = pairs(reader.get_f64s()?);
+        for x in 0..size as usize {
+            for y in 0..size as usize {
+                let id = reader.get_u64()?;
+                tracker.ids[x][y] = if id == u64::MAX { None } else { Some(id as usize) };
+            }
+        }
+        board.tracker = Some(tracker);
+    }
+
+    let species = reader.get_u64()? as usize;
+    let mut interactions = vec![vec![0.0; species]; species];
+    for row in interactions.iter_mut() {
+        for interaction in row.iter_mut() {
+            *interaction = reader.get_f64()?;
+        }
+    }
+    board.interactions = interactions;
+    board.composition = reader.get_f64s()?;
+    board.swap_probability = reader.get_f64()?;
+    board.attempted_swaps = reader.get_u64()?;
+    board.accepted_swaps = reader.get_u64()?;
+
+    //the substrate is drawn again from its seed once the lattice is known, its impurities are already among the cells read above
+    let mut substrate = None;
+    if reader.get_u8()? == 1 {
+        let blocked = reader.get_f64()?;
+        let impurities = reader.get_f64()?;
+        let kind = reader.get_u8()?;
+        let spread = reader.get_f64()?;
+        let site_energies = match kind {
+            0 => SiteEnergies::Flat,
+            1 => SiteEnergies::Uniform(spread),
+            2 => SiteEnergies::Gaussian(spread),
+            _ => return Err(invalid(&format!("unknown site energy distribution {}", kind))),
+        };
+        let seed = reader.get_u64()?;
+        substrate = Some((Substrate{blocked, impurities, site_energies}, seed));
+    }
+    let name = reader.get_str()?;
+    board.set_lattice(lattice::by_name(&name).ok_or_else(|| invalid(&format!("unknown lattice {}", name)))?).map_err(|error| invalid(&error.to_string()))?;
+    board.substrate = substrate.map(|(substrate, seed)| substrate.realise(board.lattice, size, seed));
+    let name = reader.get_str()?;
+    if !name.is_empty() {
+        board.set_superstructure(Some(Superstructure::by_name(&name).ok_or_else(|| invalid(&format!("unknown superstructure {}", name)))?));
+    }
+    let name = reader.get_str()?;
+    board.set_storage(Storage::by_name(&name).ok_or_else(|| invalid(&format!("unknown storage {}", name)))?);
+    return Ok(board);
+}
+
+//saves board to a versioned binary checkpoint, from which load resumes the run bit for bit
+pub fn save(board: &HexBoard, path: &str) -> io::Result<()> {
+    let mut writer = Writer::new();
+    write_board(&mut writer, board);
+    return writer.save(path);
+}
+
+pub fn load(path: &str) -> io::Result<HexBoard> {
+    let mut reader = Reader::open(path)?;
+    return read_board(&mut reader);
+}
@@ -0,0 +1,190 @@
+use crate::HexBoard;
+use crate::checkpoint;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+
+//why an initial configuration could not be loaded
+pub enum ConfigurationError {
+    Io(io::Error),
+    SizeMismatch { expected: u16, found: u16 },
+    OutOfRange { line: usize, x: i64, y: i64 },
+    Parse { line: usize, message: String },
+    UnknownSpecies { species: u8, count: usize },
+    Substrate { x: u16, y: u16 },
+    Duplicate { x: u16, y: u16 },
+}
+
+impl fmt::Display for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            ConfigurationError::Io(error) => write!(f, "{}", error),
+            ConfigurationError::SizeMismatch { expected, found } => write!(f, "configuration is {}x{} but the board is {}x{}", found, found, expected, expected),
+            ConfigurationError::OutOfRange { line, x, y } => write!(f, "line {}: cell ({}, {}) is outside the board", line, x, y),
+            ConfigurationError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            ConfigurationError::UnknownSpecies { species, count } => write!(f, "particle of species {} but the board has {} species", species, count),
+            ConfigurationError::Substrate { x, y } => write!(f, "particle on cell ({}, {}), which is blocked or holds an impurity", x, y),
+            ConfigurationError::Duplicate { x, y } => write!(f, "cell ({}, {}) holds more than one particle", x, y),
+        };
+    }
+}
+
+impl fmt::Debug for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return fmt::Display::fmt(self, f);
+    }
+}
+
+impl std::error::Error for ConfigurationError {}
+
+impl From<io::Error> for ConfigurationError {
+    fn from(error: io::Error) -> Self {
+        return ConfigurationError::Io(error);
+    }
+}
+
+//replaces the occupancy of board with the configuration stored in path. the format follows the extension:
+//.csv is the output of HexBoard::printfile, .ckpt a checkpoint and anything else an ascii map.
+//the fill rate of the board is updated to the number of particles loaded, whose species must exist on the board.
+//the substrate of the board is kept, so the particles must be on free sites, one per cell. the board is left untouched
+//when the configuration is rejected
+pub fn load(board: &mut HexBoard, path: &str) -> Result<(), ConfigurationError> {
+    let cells = if path.ends_with(".csv") {
+        read_csv(&fs::read_to_string(path)?, board.size)?
+    }
+    else if path.ends_with(".ckpt") {
+        read_checkpoint(path, board.size)?
+    }
+    else {
+        read_map(&fs::read_to_string(path)?, board.size)?
+    };
+
+    if let Some(&(x, y, _)) = cells.iter().find(|cell| board.is_blocked(cell.0 as i16, cell.1 as i16) || board.is_pinned(cell.0 as i16, cell.1 as i16)) {
+        return Err(ConfigurationError::Substrate { x, y });
+    }
+    if let Some(&(_, _, species)) = cells.iter().find(|cell| cell.2 as usize >= board.species_count()) {
+        return Err(ConfigurationError::UnknownSpecies { species, count: board.species_count() });
+    }
+    let mut occupied = HashSet::new();
+    if let Some(&(x, y, _)) = cells.iter().find(|cell| !occupied.insert((cell.0, cell.1))) {
+        return Err(ConfigurationError::Duplicate { x, y });
+    }
+    board.clear();
+    for &(x, y, species) in &cells {
+        board.set_cell(x as i16, y as i16, true);
+        board.set_cell_species(x as i16, y as i16, species);
+    }
+    board.fillrate = cells.len() as f32 / board.lattice.site_count(board.size) as f32;
+    return Ok(());
+}
+
+//reads the csv written by HexBoard::printfile: a header, three rows marking the corners of the plot
+//(the first one holding the board size) and then the particles with odd rows shifted by half a cell,
+//with an optional third column holding the species. lines starting with '#' are comments
+fn read_csv(text: &str, size: u16) -> Result<Vec<(u16, u16, u8)>, ConfigurationError> {
+    let mut cells = vec![];
+    //rows read so far, the header included
+    let mut rows = 0;
+    for (index, row) in text.lines().enumerate() {
+        let line = index + 1;
+        if row.trim().is_empty() || row.trim_start().starts_with('#') {
+            continue;
+        }
+        rows += 1;
+        if rows == 1 {
+            continue;
+        }
+        let values: Vec<&str> = row.split(',').map(|value| value.trim()).collect();
+        if values.len() != 2 && values.len() != 3 {
+            return Err(ConfigurationError::Parse { line, message: format!("expected 2 or 3 columns, found {}", values.len()) });
+        }
+        let x: f64 = values[0].parse().map_err(|_| ConfigurationError::Parse { line, message: format!("invalid x '{}'", values[0]) })?;
+        let y: i64 = values[1].parse().map_err(|_| ConfigurationError::Parse { line, message: format!("invalid y '{}'", values[1]) })?;
+        if rows == 2 {
+            if y != size as i64 {
+                return Err(ConfigurationError::SizeMismatch { expected: size, found: y as u16 });
+            }
+            continue;
+        }
+        if rows <= 4 {
+            continue;
+        }
+        let unshifted = if y.rem_euclid(2) == 1 { x - 0.5 } else { x };
+        if unshifted.fract() != 0.0 {
+            return Err(ConfigurationError::Parse { line, message: format!("x {} does not fall on a cell of row {}", x, y) });
+        }
+        let species: u8 = match values.get(2) {
+            Some(value) if !value.is_empty() => value.parse().map_err(|_| ConfigurationError::Parse { line, message: format!("invalid species '{}'", value) })?,
+            _ => 0,
+        };
+        let (x, y) = check_range(line, unshifted as i64, y, size)?;
+        cells.push((x, y, species));
+    }
+    return Ok(cells);
+}
+
+//reads the particles of a checkpoint, leaving parameters, random generator and substrate of the board untouched
+fn read_checkpoint(path: &str, size: u16) -> Result<Vec<(u16, u16, u8)>, ConfigurationError> {
+    let saved = checkpoint::load(path)?;
+    if saved.size != size {
+        return Err(ConfigurationError::SizeMismatch { expected: size, found: saved.size });
+    }
+    let mut cells = vec![];
+    for x in 0..size {
+        for y in 0..size {
+            let hex = saved.get_cell(x as i16, y as i16);
+            if saved.is_particle(hex) {
+                cells.push((x, y, hex.species));
+            }
+        }
+    }
+    return Ok(cells);
+}
+
+//reads an ascii map with one line per row y and one character per column x, 'o' for a particle and '.' for an empty cell,
+//or 'A', 'B', ... for particles of species 0, 1, ... of a mixture. blocked sites 'x' and impurities '*' are read as
+//empty cells, the substrate comes from its seed and not from the map. spaces are ignored, so odd rows can be indented
+//to show the hexagonal layout, and lines starting with '#' are comments
+fn read_map(text: &str, size: u16) -> Result<Vec<(u16, u16, u8)>, ConfigurationError> {
+    let mut cells = vec![];
+    let mut y: i64 = 0;
+    for (index, row) in text.lines().enumerate() {
+        let line = index + 1;
+        if row.trim().is_empty() || row.trim_start().starts_with('#') {
+            continue;
+        }
+        let mut x: i64 = 0;
+        for character in row.chars().filter(|c| !c.is_whitespace()) {
+            match character {
+                'o' => {
+                    let (x, y) = check_range(line, x, y, size)?;
+                    cells.push((x, y, 0));
+                }
+                'A'..='Z' => {
+                    let (x, y) = check_range(line, x, y, size)?;
+                    cells.push((x, y, character as u8 - b'A'));
+                }
+                '.' | 'x' | '*' => { check_range(line, x, y, size)?; }
+                _ => return Err(ConfigurationError::Parse { line, message: format!("unexpected character '{}'", character) }),
+            }
+            x += 1;
+        }
+        if x != size as i64 {
+            return Err(ConfigurationError::SizeMismatch { expected: size, found: x as u16 });
+        }
+        y += 1;
+    }
+    if y != size as i64 {
+        return Err(ConfigurationError::SizeMismatch { expected: size, found: y as u16 });
+    }
+    return Ok(cells);
+}
+
+fn check_range(line: usize, x: i64, y: i64, size: u16) -> Result<(u16, u16), ConfigurationError> {
+    if x < 0 || y < 0 || x >= size as i64 || y >= size as i64 {
+        return Err(ConfigurationError::OutOfRange { line, x, y });
+    }
+    return Ok((x as u16, y as u16));
+}
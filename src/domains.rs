@@ -0,0 +1,182 @@
+use crate::HexBoard;
+use crate::hexboard::Hex;
+use crate::error::Result;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::prelude::*;
+
+//domains of the sublattice orderings, found with a union-find labelling pass over the occupied sites
+pub struct Domains {
+    pub labels: Vec<Vec<Option<usize>>>,
+    pub sizes: Vec<usize>,
+    pub sublattices: Vec<u8>,
+    pub wall_length: u32,
+    pub sublattice_count: usize,
+}
+
+impl Domains {
+
+    //labels every particle of the board with the domain it belongs to, impurities of the substrate are left out.
+    //occupied sites on the same sublattice are joined when they are within the sublattice reach of each other,
+    //which for an ordered phase are the closest sites of the superstructure, the sqrt(3) neighbours at fill 1/3
+    pub fn new(board: &HexBoard) -> Self {
+        let size = board.size as usize;
+        let mut sets = UnionFind::new(size * size);
+        let mut walls: HashSet<(usize, usize)> = HashSet::new();
+
+        for x in 0..board.size {
+            for y in 0..board.size {
+                let hex = board.get_cell(x as i16, y as i16);
+                if !board.is_particle(hex) {
+                    continue;
+                }
+                let index = x as usize * size + y as usize;
+                let sublattice = board.get_sublattice(x as i16, y as i16);
+                for close in Self::get_close_sites(board, x as i16, y as i16) {
+                    let other = close.x as usize * size + close.y as usize;
+                    if !board.is_particle(close) || other == index {
+                        continue;
+                    }
+                    if board.get_sublattice(close.x, close.y) == sublattice {
+                        sets.union(index, other);
+                    }
+                    else {
+                        walls.insert((index.min(other), index.max(other)));
+                    }
+                }
+            }
+        }
+
+        //turn the union-find roots into consecutive domain ids
+        let mut labels = vec![vec![None; size]; size];
+        let mut ids: Vec<Option<usize>> = vec![None; size * size];
+        let mut sizes: Vec<usize> = vec![];
+        let mut sublattices: Vec<u8> = vec![];
+        for (x, column) in labels.iter_mut().enumerate() {
+            for (y, label) in column.iter_mut().enumerate() {
+                if !board.is_particle(board.get_cell(x as i16, y as i16)) {
+                    continue;
+                }
+                let root = sets.find(x * size + y);
+                let id = match ids[root] {
+                    Some(id) => id,
+                    None => {
+                        ids[root] = Some(sizes.len());
+                        sizes.push(0);
+                        sublattices.push(board.get_sublattice(x as i16, y as i16));
+                        sizes.len() - 1
+                    }
+                };
+                sizes[id] += 1;
+                *label = Some(id);
+            }
+        }
+
+        return Domains{labels, sizes, sublattices, wall_length: walls.len() as u32, sublattice_count: board.get_sublattice_count()};
+    }
+
+    //returns the sites reachable from x, y in up to get_sublattice_reach nearest neighbour steps, two for the sqrt(3) ordering
+    fn get_close_sites(board: &HexBoard, x: i16, y: i16) -> Vec<Hex> {
+        let mut close = vec![];
+        for shell in 0..board.get_sublattice_reach() {
+            close.extend(board.get_shell(x, y, shell));
+        }
+        return close;
+    }
+
+    //number of domains on the board
+    pub fn count(&self) -> usize {
+        return self.sizes.len();
+    }
+
+    //average number of particles in a domain
+    pub fn mean_size(&self) -> f64 {
+        if self.sizes.is_empty() {
+            return 0.0;
+        }
+        return self.sizes.iter().sum::<usize>() as f64 / self.sizes.len() as f64;
+    }
+
+    //number of domains ordered on each sublattice
+    pub fn count_per_sublattice(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.sublattice_count];
+        for &sublattice in &self.sublattices {
+            counts[sublattice as usize] += 1;
+        }
+        return counts;
+    }
+
+    //returns the pairs (domain size, number of domains with that size) sorted by size
+    pub fn size_distribution(&self) -> Vec<(usize, usize)> {
+        let mut distribution: Vec<(usize, usize)> = vec![];
+        let mut sizes = self.sizes.clone();
+        sizes.sort();
+        for size in sizes {
+            match distribution.last_mut() {
+                Some(last) if last.0 == size => last.1 += 1,
+                _ => distribution.push((size, 1)),
+            }
+        }
+        return distribution;
+    }
+
+    //writes the domain id of every occupied site, with the same shifted coordinates as HexBoard::printfile
+    pub fn printfile(&self, filename: &str) -> Result<()> {
+        let mut file = File::create(filename)?;
+        file.write_all(b"x,y,domain,sublattice\n")?;
+        for x in 0..self.labels.len() {
+            for y in 0..self.labels[x].len() {
+                if let Some(id) = self.labels[x][y] {
+                    let shifted_x = if y % 2 == 1 { x as f32 + 0.5 } else { x as f32 };
+                    let row = format!("{},{},{},{}\n", shifted_x, y, id, self.sublattices[id]);
+                    file.write_all(row.as_bytes())?;
+                }
+            }
+        }
+        file.flush()?;
+        return Ok(());
+    }
+}
+
+//disjoint set forest with path compression and union by size
+struct UnionFind {
+    parent: Vec<usize>,
+    weight: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(elements: usize) -> Self {
+        return UnionFind{parent: (0..elements).collect(), weight: vec![1; elements]};
+    }
+
+    fn find(&mut self, element: usize) -> usize {
+        let mut root = element;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut current = element;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        return root;
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let root_a = self.find(a);
+        let root_b = self.find(b);
+        if root_a == root_b {
+            return;
+        }
+        if self.weight[root_a] < self.weight[root_b] {
+            self.parent[root_a] = root_b;
+            self.weight[root_b] += self.weight[root_a];
+        }
+        else {
+            self.parent[root_b] = root_a;
+            self.weight[root_a] += self.weight[root_b];
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::HexBoard;
+use crate::error::{Error, Result};
+use crate::random::Generator;
+use crate::statistics::{self, mean};
+
+use std::fmt;
+
+//minimum number of per-sweep samples before the statistical criteria are evaluated, so that the first 10% window of
+//the Geweke diagnostic holds at least 10 samples
+const MIN_SAMPLES: usize = 100;
+//the Geweke diagnostic goes over every sample, so it is only repeated once the samples have grown by this factor since
+//the last check. this keeps its total cost linear in the length of the burn-in instead of quadratic
+const GEWEKE_GROWTH: f64 = 1.1;
+//flipped in the stream of a board to give its cold start replica a stream of its own
+const COLD_STREAM: u64 = 1 << 63;
+
+//conditions that end the burn-in of a simulation, energy and order are sampled once per sweep
+#[derive(Clone, Copy)]
+pub enum Criterion {
+    //any of the sublattice fractions of get_order exceeds the threshold
+    OrderThreshold(f32),
+    //the mean energy of the last window sweeps differs from the previous window by less than tolerance (relative)
+    Plateau { window: usize, tolerance: f64 },
+    //Geweke diagnostic: the means of the first 10% and last 50% of the energy samples agree within z standard errors
+    Geweke { z: f64 },
+    //a replica started from a perfectly ordered configuration reaches the same mean energy over window sweeps
+    Replicas { window: usize, tolerance: f64 },
+    //stop after this many steps whatever happens
+    MaxSteps(i32),
+}
+
+//why the burn-in ended, with the step at which it happened
+pub enum StopReason {
+    OrderThreshold { step: i32, order: f32 },
+    Plateau { step: i32, change: f64 },
+    Geweke { step: i32, z: f64 },
+    Replicas { step: i32, difference: f64 },
+    MaxSteps { step: i32 },
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            StopReason::OrderThreshold { step, order } => write!(f, "sublattice order {:.3} reached after {} steps", order, step),
+            StopReason::Plateau { step, change } => write!(f, "energy plateau (relative change {:.4}) after {} steps", change, step),
+            StopReason::Geweke { step, z } => write!(f, "Geweke drift test passed (z = {:.2}) after {} steps", z, step),
+            StopReason::Replicas { step, difference } => write!(f, "hot and cold starts agree (energy difference {:.4}) after {} steps", difference, step),
+            StopReason::MaxSteps { step } => write!(f, "maximum of {} steps reached", step),
+        };
+    }
+}
+
+//checks a running simulation against a list of criteria, the first one satisfied ends the burn-in
+#[derive(Clone)]
+pub struct Equilibration {
+    criteria: Vec<Criterion>,
+    energy: Vec<f64>,
+    replica: Option<HexBoard>,
+    replica_energy: Vec<f64>,
+    //number of samples at which the Geweke diagnostic is evaluated next
+    next_geweke: usize,
+}
+
+impl Equilibration {
+    //fails on the windows of Plateau and Replicas being empty, they would average over no samples
+    pub fn new(criteria: Vec<Criterion>) -> Result<Self> {
+        for criterion in &criteria {
+            if let Criterion::Plateau { window: 0, .. } | Criterion::Replicas { window: 0, .. } = criterion {
+                return Err(Error::Parameter("the window of a burn-in criterion must hold at least one sweep".to_string()));
+            }
+        }
+        return Ok(Equilibration{criteria, energy: vec![], replica: None, replica_energy: vec![], next_geweke: MIN_SAMPLES});
+    }
+
+    //runs the burn-in of board until one of the criteria stops it
+    pub fn run(&mut self, board: &mut HexBoard) -> StopReason {
+        let mut step = 0;
+        loop {
+            board.advance_timestep_repulsive();
+            step += 1;
+            if let Some(reason) = self.check(board, step) {
+                return reason;
+            }
+        }
+    }
+
+    //to be called after every step of board, returns why the simulation is equilibrated once a criterion is met
+    pub fn check(&mut self, board: &HexBoard, step: i32) -> Option<StopReason> {
+        let sweep = board.size as i32 * board.size as i32;
+        let uses_replica = self.criteria.iter().any(|c| matches!(c, Criterion::Replicas { .. }));
+        if uses_replica {
+            let replica = self.replica.get_or_insert_with(|| cold_start(board));
+            replica.advance_timestep_repulsive();
+        }
+        let sampled = step % sweep == 0;
+        if sampled {
+            self.energy.push(board.total_energy());
+            if let Some(replica) = self.replica.as_ref() {
+                self.replica_energy.push(replica.total_energy());
+            }
+        }
+
+        for criterion in self.criteria.clone() {
+            let reason = match criterion {
+                Criterion::MaxSteps(max) if step >= max => Some(StopReason::MaxSteps { step }),
+                Criterion::OrderThreshold(threshold) => {
+                    let order = board.get_order().into_iter().fold(0.0, f32::max);
+                    if order > threshold { Some(StopReason::OrderThreshold { step, order }) } else { None }
+                }
+                Criterion::Plateau { window, tolerance } if sampled => self.check_plateau(step, window, tolerance),
+                Criterion::Geweke { z } if sampled => self.check_geweke(step, z),
+                Criterion::Replicas { window, tolerance } if sampled => self.check_replicas(step, window, tolerance),
+                _ => None,
+            };
+            if reason.is_some() {
+                return reason;
+            }
+        }
+        return None;
+    }
+
+    fn check_plateau(&self, step: i32, window: usize, tolerance: f64) -> Option<StopReason> {
+        if self.energy.len() < 2 * window {
+            return None;
+        }
+        let last = mean(&self.energy[self.energy.len() - window..]);
+        let previous = mean(&self.energy[self.energy.len() - 2 * window..self.energy.len() - window]);
+        let change = (last - previous).abs() / previous.abs().max(1.0);
+        if change < tolerance {
+            return Some(StopReason::Plateau { step, change });
+        }
+        return None;
+    }
+
+    fn check_geweke(&mut self, step: i32, threshold: f64) -> Option<StopReason> {
+        if self.energy.len() < self.next_geweke {
+            return None;
+        }
+        self.next_geweke = ((self.energy.len() as f64 * GEWEKE_GROWTH) as usize).max(self.energy.len() + 1);
+        let first = &self.energy[..self.energy.len() / 10];
+        let last = &self.energy[self.energy.len() / 2..];
+        let error = standard_error(first).powi(2) + standard_error(last).powi(2);
+        let difference = mean(first) - mean(last);
+        let z = if error > 0.0 { difference / error.sqrt() } else if difference == 0.0 { 0.0 } else { f64::INFINITY };
+        if z.abs() < threshold {
+            return Some(StopReason::Geweke { step, z });
+        }
+        return None;
+    }
+
+    fn check_replicas(&self, step: i32, window: usize, tolerance: f64) -> Option<StopReason> {
+        if self.energy.len() < window.max(MIN_SAMPLES) {
+            return None;
+        }
+        let hot = mean(&self.energy[self.energy.len() - window..]);
+        let cold = mean(&self.replica_energy[self.replica_energy.len() - window..]);
+        let difference = (hot - cold).abs() / hot.abs().max(1.0);
+        if difference < tolerance {
+            return Some(StopReason::Replicas { step, difference });
+        }
+        return None;
+    }
+}
+
+//standard error of the mean of correlated data, corrected with its autocorrelation time
+fn standard_error(data: &[f64]) -> f64 {
+    let variance = statistics::variance(data);
+    let effective = statistics::effective_sample_size(data.len(), statistics::integrated_autocorrelation_time(data));
+    return (variance / effective).sqrt();
+}
+
+//copy of board with the same number of particles packed on the first sublattice, as a cold start replica. the replica
+//runs on the stream of board with its top bit flipped, which no batch replica or parallel block uses, so the two starts
+//are driven by independent moves
+fn cold_start(board: &HexBoard) -> HexBoard {
+    let mut replica = board.clone();
+    replica.rng = Generator::new(board.rng.backend(), board.rng.seed(), board.rng.stream() ^ COLD_STREAM);
+    replica.tracker = None;
+    replica.initialize_ordered(0);
+    return replica;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Backend;
+    use rand::Rng;
+
+    #[test]
+    fn empty_windows_are_rejected() {
+        assert!(Equilibration::new(vec![Criterion::Replicas { window: 0, tolerance: 0.1 }]).is_err());
+        assert!(Equilibration::new(vec![Criterion::Plateau { window: 0, tolerance: 0.1 }]).is_err());
+        assert!(Equilibration::new(vec![Criterion::Replicas { window: 1, tolerance: 0.1 }, Criterion::MaxSteps(10)]).is_ok());
+    }
+
+    #[test]
+    fn cold_start_draws_from_its_own_stream() {
+        let mut board = HexBoard::new(6, Generator::new(Backend::ChaCha12, 3, 5), 1.0, 1.0 / 3.0);
+        let mut replica = cold_start(&board);
+        let draws = |rng: &mut Generator| -> Vec<u64> { return (0..4).map(|_| rng.gen()).collect(); };
+        assert_ne!(draws(&mut board.rng), draws(&mut replica.rng));
+    }
+}
@@ -0,0 +1,74 @@
+use crate::configuration::ConfigurationError;
+
+use plotters::prelude::DrawingAreaErrorKind;
+use std::fmt;
+use std::io;
+
+//errors of the file, plot and batch apis, reported by main instead of stopping the program
+pub enum Error {
+    Io(io::Error),
+    Configuration(ConfigurationError),
+    Plot(String),
+    //a setting that the simulation cannot run with
+    Parameter(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Error::Io(error) => write!(f, "{}", error),
+            Error::Configuration(error) => write!(f, "{}", error),
+            Error::Plot(message) => write!(f, "could not draw plot: {}", message),
+            Error::Parameter(message) => write!(f, "invalid parameter: {}", message),
+        };
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return fmt::Display::fmt(self, f);
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            Error::Io(error) => Some(error),
+            Error::Configuration(error) => Some(error),
+            Error::Plot(_) | Error::Parameter(_) => None,
+        };
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        return Error::Io(error);
+    }
+}
+
+impl From<ConfigurationError> for Error {
+    fn from(error: ConfigurationError) -> Self {
+        return Error::Configuration(error);
+    }
+}
+
+//plotters wraps the errors of its backend, they are kept as text since the backend types are generic
+impl<E: std::error::Error + Send + Sync> From<DrawingAreaErrorKind<E>> for Error {
+    fn from(error: DrawingAreaErrorKind<E>) -> Self {
+        return Error::Plot(error.to_string());
+    }
+}
+
+//prints a failed output, batch or setting to stderr, so the program goes on with the remaining ones instead of stopping.
+//every error the program does not return goes through here
+pub fn report<T, E: fmt::Display>(what: &str, result: std::result::Result<T, E>) -> Option<T> {
+    return match result {
+        Ok(value) => Some(value),
+        Err(error) => {
+            eprintln!("{}: {}", what, error);
+            None
+        }
+    };
+}
@@ -0,0 +1,207 @@
+use crate::hexboard::HexBoard;
+
+//largest number of configurations Enumeration::new walks through, about a second per million
+const MAX_CONFIGURATIONS: u128 = 5_000_000;
+
+//energy of the configuration of board, the hamiltonian sampled by advance_timestep_repulsive: the coupling times every bond
+//between occupied neighbours, impurities included, minus the field on the particles of its sublattice, plus the on-site
+//energies of the particles. differences of it are the deltas of the jumps
+pub fn energy(board: &HexBoard) -> f64 {
+    let mut energy = board.coupling * board.total_energy() / 2.0;
+    for hex in board.grid.hexes() {
+        if board.is_particle(hex) {
+            energy += board.get_site_energy(hex);
+            energy -= board.field * board.get_field_weight(hex);
+        }
+    }
+    return energy;
+}
+
+//number of ways to choose k of n sites, saturating far above any board that can be enumerated
+fn binomial(n: usize, k: usize) -> u128 {
+    let mut count: u128 = 1;
+    for i in 0..k.min(n - k) {
+        count = (count * (n - i) as u128 / (i + 1) as u128).min(u128::MAX / n as u128);
+    }
+    return count;
+}
+
+//exact canonical averages of a small board, from every placement of its particles on the sites left free by the substrate,
+//at the geometry, couplings and betaj of the board. impurities stay where they are
+pub struct Enumeration {
+    pub configurations: usize,
+    pub log_partition_function: f64,
+    pub mean_energy: f64,
+    pub energy_variance: f64,
+    //the distinct values of |psi| with their probabilities, in increasing order of |psi|
+    pub psi_distribution: Vec<(f64, f64)>,
+}
+
+impl Enumeration {
+    pub fn new(board: &HexBoard) -> Self {
+        assert_eq!(board.species_count(), 1, "only boards with a single species can be enumerated");
+        let mut board = board.clone();
+        let mut sites = vec![];
+        let mut particles = 0;
+        for x in 0..board.size as i16 {
+            for y in 0..board.size as i16 {
+                if board.is_particle(board.get_cell(x, y)) {
+                    particles += 1;
+                    board.set_cell(x, y, false);
+                }
+                if !board.is_blocked(x, y) && !board.is_pinned(x, y) {
+                    sites.push((x, y));
+                }
+            }
+        }
+        assert!(binomial(sites.len(), particles) <= MAX_CONFIGURATIONS, "too many configurations to enumerate");
+
+        //the particles sit on sites[chosen[0]], sites[chosen[1]], ... in increasing order
+        let mut chosen: Vec<usize> = (0..particles).collect();
+        let mut energies = vec![];
+        let mut psis = vec![];
+        loop {
+            for &i in &chosen {
+                board.set_cell(sites[i].0, sites[i].1, true);
+            }
+            energies.push(energy(&board));
+            psis.push(board.get_order_parameter());
+            for &i in &chosen {
+                board.set_cell(sites[i].0, sites[i].1, false);
+            }
+            //next combination: move up the last particle that can still move and pack the following ones behind it
+            match (0..particles).rev().find(|&k| chosen[k] < sites.len() - particles + k) {
+                Some(k) => {
+                    chosen[k] += 1;
+                    for next in k + 1..particles {
+                        chosen[next] = chosen[next - 1] + 1;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        //weights relative to the ground state, so the exponentials cannot overflow
+        let beta = board.disorder;
+        let ground = energies.iter().cloned().fold(f64::INFINITY, f64::min);
+        let weights: Vec<f64> = energies.iter().map(|e| (-beta * (e - ground)).exp()).collect();
+        let total: f64 = weights.iter().sum();
+        let mean_energy = energies.iter().zip(&weights).map(|(e, w)| e * w).sum::<f64>() / total;
+        let energy_variance = energies.iter().zip(&weights).map(|(e, w)| (e - mean_energy).powi(2) * w).sum::<f64>() / total;
+
+        let mut weighted_psis: Vec<(f64, f64)> = psis.into_iter().zip(weights.iter().map(|w| w / total)).collect();
+        weighted_psis.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut psi_distribution: Vec<(f64, f64)> = vec![];
+        for (psi, probability) in weighted_psis {
+            match psi_distribution.last_mut() {
+                Some(last) if psi - last.0 < 1e-9 => last.1 += probability,
+                _ => psi_distribution.push((psi, probability)),
+            }
+        }
+
+        return Enumeration{configurations: energies.len(), log_partition_function: total.ln() - beta * ground, mean_energy, energy_variance, psi_distribution};
+    }
+
+    pub fn mean_psi(&self) -> f64 {
+        return self.psi_distribution.iter().map(|(psi, probability)| psi * probability).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batchrun;
+    use crate::hexboard::Boundary;
+    use crate::lattice;
+    use crate::statistics::ErrorMethod;
+    use crate::substrate::{SiteEnergies, Substrate};
+    use crate::random::{Backend, Generator};
+
+    //runs the sampler on board and checks <E>, <|psi|>, the specific heat and every likely value of |psi| against the enumeration,
+    //within 4 standard errors of the blocked per sweep samples
+    fn compare(mut board: HexBoard, sweeps: usize) {
+        board.initialize();
+        let exact = Enumeration::new(&board);
+        let sweep = board.size as usize * board.size as usize;
+        let mut energies = vec![];
+        let mut psis = vec![];
+        for i in 0..sweeps + 1000 {
+            for _ in 0..sweep {
+                board.advance_timestep_repulsive();
+            }
+            if i >= 1000 {
+                energies.push(energy(&board));
+                psis.push(board.get_order_parameter());
+            }
+        }
+        let sites = board.lattice.site_count(board.size);
+        let mut checks = vec![
+            ("<E>", ErrorMethod::Blocking.estimate(&energies), exact.mean_energy),
+            ("<|psi|>", ErrorMethod::Blocking.estimate(&psis), exact.mean_psi()),
+            ("C", batchrun::specific_heat(ErrorMethod::Blocking, &energies, board.disorder, sites), board.disorder * board.disorder * exact.energy_variance / sites as f64),
+        ];
+        let indices: Vec<usize> = psis.iter()
+            .map(|&psi| exact.psi_distribution.iter().position(|&(value, _)| (value - psi).abs() < 1e-6).expect("sampled a |psi| the enumeration never reached"))
+            .collect();
+        for (index, &(_, probability)) in exact.psi_distribution.iter().enumerate() {
+            if probability > 0.05 {
+                let hits: Vec<f64> = indices.iter().map(|&i| if i == index { 1.0 } else { 0.0 }).collect();
+                checks.push(("P(|psi|)", ErrorMethod::Blocking.estimate(&hits), probability));
+            }
+        }
+        for (name, estimate, expected) in checks {
+            assert!((estimate.mean - expected).abs() < 4.0 * estimate.error + 1e-9,
+                "{}: sampled {:.5} +- {:.5}, exact {:.5}", name, estimate.mean, estimate.error, expected);
+        }
+    }
+
+    #[test]
+    fn energy_differences_are_jump_deltas() {
+        let mut board = HexBoard::new(6, Generator::new(Backend::ChaCha12, 11, 0), 1.0, 1.0 / 3.0);
+        board.set_field(0.7);
+        board.set_coupling(1.3);
+        board.set_substrate(Substrate{site_energies: SiteEnergies::Uniform(1.0), ..Substrate::clean()}, 5);
+        board.initialize();
+        let before = energy(&board);
+        let (from, dest) = (0..6).flat_map(|x| (0..6).map(move |y| (x, y)))
+            .find_map(|(x, y)| {
+                let hex = board.get_cell(x, y);
+                let free = board.get_neighbours(x, y).into_iter().find(|n| !n.value);
+                return if hex.value { free.map(|free| (hex, free)) } else { None };
+            })
+            .unwrap();
+        let delta = board.coupling * (board.get_energy(dest) - 1.0 - board.get_energy(from))
+            + board.field * (board.get_field_weight(from) - board.get_field_weight(dest))
+            + board.get_site_energy(dest) - board.get_site_energy(from);
+        board.set_cell(from.x, from.y, false);
+        board.set_cell(dest.x, dest.y, true);
+        assert!((energy(&board) - before - delta).abs() < 1e-9);
+    }
+
+    #[test]
+    fn partition_function_of_a_single_particle() {
+        //one particle on a field free board without neighbours has the same energy everywhere
+        let mut board = HexBoard::new(4, Generator::new(Backend::ChaCha12, 1, 0), 2.0, 1.0 / 16.0);
+        board.initialize();
+        let exact = Enumeration::new(&board);
+        assert_eq!(exact.configurations, 16);
+        assert!((exact.log_partition_function - 16f64.ln()).abs() < 1e-12);
+        assert!(exact.mean_energy.abs() < 1e-12 && exact.energy_variance.abs() < 1e-12);
+    }
+
+    #[test]
+    fn sampler_matches_enumeration_on_the_triangular_lattice() {
+        let board = HexBoard::new(6, Generator::new(Backend::ChaCha12, 2024, 0), 1.0, 4.0 / 36.0);
+        compare(board, 40000);
+    }
+
+    #[test]
+    fn sampler_matches_enumeration_on_an_open_square_substrate() {
+        let mut board = HexBoard::new(4, Generator::new(Backend::ChaCha12, 7, 0), 1.5, 0.5);
+        board.set_lattice(&lattice::SQUARE).unwrap();
+        board.set_boundary(Boundary::Open);
+        board.set_field(0.5);
+        board.set_substrate(Substrate{blocked: 0.0625, impurities: 0.0625, site_energies: SiteEnergies::Gaussian(0.5)}, 3);
+        compare(board, 40000);
+    }
+}
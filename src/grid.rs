@@ -0,0 +1,315 @@
+use crate::hexboard::Hex;
+
+use std::fmt;
+
+//alternating bits of a word, the cells with even and odd x
+const EVEN: u64 = 0x5555_5555_5555_5555;
+const ODD: u64 = 0xAAAA_AAAA_AAAA_AAAA;
+
+//how the cells of a board are kept in memory
+#[derive(Clone, Copy, PartialEq)]
+pub enum Storage {
+    //a Hex per cell in nested vectors, 6 bytes a cell
+    Cells,
+    //one occupancy bit per cell packed in 64 bit words along the rows, the species as extra bit planes once there are several
+    Packed,
+}
+
+impl Storage {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Storage::Cells => "cells",
+            Storage::Packed => "packed",
+        };
+    }
+
+    pub fn by_name(name: &str) -> Option<Storage> {
+        return [Storage::Cells, Storage::Packed].into_iter().find(|storage| storage.name() == name);
+    }
+}
+
+impl fmt::Display for Storage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Storage::Cells => write!(f, "one cell per site"),
+            Storage::Packed => write!(f, "bit-packed occupancy"),
+        };
+    }
+}
+
+//the cells of a board, indexed by x and y like the board
+#[derive(Clone)]
+pub enum Grid {
+    Cells(Vec<Vec<Hex>>),
+    Packed(Packed),
+}
+
+impl Grid {
+    pub fn new(size: u16, storage: Storage) -> Self {
+        return match storage {
+            Storage::Cells => Grid::Cells((0..size as i16).map(|x| (0..size as i16).map(|y| Hex::new(x, y)).collect()).collect()),
+            Storage::Packed => Grid::Packed(Packed::new(size)),
+        };
+    }
+
+    pub fn storage(&self) -> Storage {
+        return match self {
+            Grid::Cells(_) => Storage::Cells,
+            Grid::Packed(_) => Storage::Packed,
+        };
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Hex {
+        return match self {
+            Grid::Cells(cells) => cells[x][y],
+            Grid::Packed(packed) => packed.get(x, y),
+        };
+    }
+
+    pub fn set_value(&mut self, x: usize, y: usize, value: bool) {
+        match self {
+            Grid::Cells(cells) => cells[x][y].value = value,
+            Grid::Packed(packed) => packed.set_value(x, y, value),
+        }
+    }
+
+    pub fn set_species(&mut self, x: usize, y: usize, species: u8) {
+        match self {
+            Grid::Cells(cells) => cells[x][y].species = species,
+            Grid::Packed(packed) => packed.set_species(x, y, species),
+        }
+    }
+
+    //writes the occupation and species of hex to its cell
+    pub fn set(&mut self, hex: Hex) {
+        self.set_value(hex.x as usize, hex.y as usize, hex.value);
+        self.set_species(hex.x as usize, hex.y as usize, hex.species);
+    }
+
+    //empties every cell and resets its species
+    pub fn clear(&mut self) {
+        match self {
+            Grid::Cells(cells) => {
+                for hex in cells.iter_mut().flatten() {
+                    hex.value = false;
+                    hex.species = 0;
+                }
+            }
+            Grid::Packed(packed) => packed.clear(),
+        }
+    }
+
+    //every cell, column by column
+    pub fn hexes(&self) -> impl Iterator<Item = Hex> + '_ {
+        let size = self.size();
+        return (0..size).flat_map(move |x| (0..size).map(move |y| self.get(x, y)));
+    }
+
+    pub fn size(&self) -> usize {
+        return match self {
+            Grid::Cells(cells) => cells.len(),
+            Grid::Packed(packed) => packed.size,
+        };
+    }
+
+    //the same cells kept in storage
+    pub fn converted(&self, storage: Storage) -> Grid {
+        let mut grid = Grid::new(self.size() as u16, storage);
+        for hex in self.hexes() {
+            grid.set(hex);
+        }
+        return grid;
+    }
+
+    //bytes taken by the cells
+    pub fn bytes(&self) -> usize {
+        return match self {
+            Grid::Cells(cells) => cells.len() * (std::mem::size_of::<Vec<Hex>>() + cells.len() * std::mem::size_of::<Hex>()),
+            Grid::Packed(packed) => packed.occupied.len() * (1 + packed.species.len()) * 8,
+        };
+    }
+}
+
+//occupancy bits of a size x size board, row y holding the cells x = 0.. in words y * words.., bit x % 64 of word x / 64.
+//bit k of the species of every cell is kept in plane k, planes are only added once a species needs them
+#[derive(Clone)]
+pub struct Packed {
+    size: usize,
+    words: usize,
+    occupied: Vec<u64>,
+    species: Vec<Vec<u64>>,
+}
+
+impl Packed {
+    fn new(size: u16) -> Self {
+        let words = (size as usize).div_ceil(64);
+        return Packed{size: size as usize, words, occupied: vec![0; words * size as usize], species: vec![]};
+    }
+
+    //word and bit of the cell x, y
+    fn locate(&self, x: usize, y: usize) -> (usize, u64) {
+        return (y * self.words + x / 64, 1 << (x % 64));
+    }
+
+    fn get(&self, x: usize, y: usize) -> Hex {
+        let (word, bit) = self.locate(x, y);
+        let mut species = 0;
+        for (k, plane) in self.species.iter().enumerate() {
+            if plane[word] & bit != 0 {
+                species |= 1 << k;
+            }
+        }
+        return Hex{x: x as i16, y: y as i16, value: self.occupied[word] & bit != 0, species};
+    }
+
+    fn set_value(&mut self, x: usize, y: usize, value: bool) {
+        let (word, bit) = self.locate(x, y);
+        if value {
+            self.occupied[word] |= bit;
+        }
+        else {
+            self.occupied[word] &= !bit;
+        }
+    }
+
+    fn set_species(&mut self, x: usize, y: usize, species: u8) {
+        let (word, bit) = self.locate(x, y);
+        while self.species.len() < (8 - species.leading_zeros()) as usize {
+            self.species.push(vec![0; self.occupied.len()]);
+        }
+        for (k, plane) in self.species.iter_mut().enumerate() {
+            if species & (1 << k) != 0 {
+                plane[word] |= bit;
+            }
+            else {
+                plane[word] &= !bit;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.occupied.iter_mut().for_each(|word| *word = 0);
+        self.species.clear();
+    }
+
+    fn row(&self, y: usize) -> &[u64] {
+        return &self.occupied[y * self.words..(y + 1) * self.words];
+    }
+
+    //row y seen from dx cells to the left, bit x holding the cell x + dx. cells beyond the edge wrap around
+    //on a periodic board and are empty on an open one
+    fn shifted(&self, y: usize, dx: i16, periodic: bool) -> Vec<u64> {
+        let row = self.row(y);
+        let last = self.size - 1;
+        let mut shifted = row.to_vec();
+        match dx {
+            1 => {
+                for w in 0..self.words {
+                    shifted[w] = (row[w] >> 1) | row.get(w + 1).map_or(0, |next| next << 63);
+                }
+                if periodic && row[0] & 1 != 0 {
+                    shifted[last / 64] |= 1 << (last % 64);
+                }
+            }
+            -1 => {
+                for w in 0..self.words {
+                    shifted[w] = (row[w] << 1) | if w > 0 { row[w - 1] >> 63 } else { 0 };
+                }
+                //the last cell moved past the edge of the row
+                if self.size % 64 != 0 {
+                    shifted[self.words - 1] &= (1 << (self.size % 64)) - 1;
+                }
+                if periodic && row[last / 64] & (1 << (last % 64)) != 0 {
+                    shifted[0] |= 1;
+                }
+            }
+            _ => {}
+        }
+        return shifted;
+    }
+
+    //number of ordered pairs of occupied neighbours, the sum over the occupied cells of their occupied neighbours, counted
+    //a word at a time. directions(px, py) gives the neighbour offsets of the cells with x and y of parities px and py,
+    //which must be the same for every such cell and move by at most one cell along x
+    pub fn neighbour_pairs(&self, directions: impl Fn(i16, i16) -> &'static [(i16, i16)], periodic: bool) -> u64 {
+        let mut pairs = 0;
+        for y in 0..self.size {
+            for (parity, mask) in [(0, EVEN), (1, ODD)] {
+                for &(dx, dy) in directions(parity, (y % 2) as i16) {
+                    let other = y as i64 + dy as i64;
+                    if !periodic && (other < 0 || other >= self.size as i64) {
+                        continue;
+                    }
+                    let neighbours = self.shifted(other.rem_euclid(self.size as i64) as usize, dx, periodic);
+                    pairs += self.row(y).iter().zip(&neighbours).map(|(cells, neighbours)| (cells & mask & neighbours).count_ones() as u64).sum::<u64>();
+                }
+            }
+        }
+        return pairs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hexboard::{Boundary, HexBoard};
+    use crate::lattice;
+    use crate::random::{Backend, Generator};
+
+    #[test]
+    fn packed_cells_read_back() {
+        let mut cells = Grid::new(70, Storage::Cells);
+        let mut packed = Grid::new(70, Storage::Packed);
+        for (i, (x, y)) in [(0, 0), (69, 0), (63, 5), (64, 5), (12, 69), (69, 69)].into_iter().enumerate() {
+            for grid in [&mut cells, &mut packed] {
+                grid.set_value(x, y, true);
+                grid.set_species(x, y, (i % 4) as u8);
+            }
+        }
+        packed.set_value(12, 69, false);
+        cells.set_value(12, 69, false);
+        assert!(cells.hexes().zip(packed.hexes()).all(|(a, b)| a.x == b.x && a.y == b.y && a.value == b.value && a.species == b.species));
+        assert!(packed.converted(Storage::Cells).hexes().zip(cells.hexes()).all(|(a, b)| a.value == b.value && a.species == b.species));
+        //two words a row for the occupancy and each of the two species planes
+        assert_eq!(packed.bytes(), 70 * 2 * 3 * 8);
+        assert!(packed.bytes() * 8 < cells.bytes());
+    }
+
+    //the word-level count of total_energy against the count cell by cell, with rows of one, two and three words
+    #[test]
+    fn word_level_energy_matches_cells() {
+        for lattice in lattice::builtin() {
+            for boundary in [Boundary::Periodic, Boundary::Open] {
+                for size in [8, 9, 64, 70, 130] {
+                    //only the square lattice wraps periodically at odd sizes
+                    if boundary == Boundary::Periodic && size % 2 == 1 && lattice.needs_even_size() {
+                        continue;
+                    }
+                    let mut board = HexBoard::with_storage(size, Generator::new(Backend::ChaCha12, size as u64, 0), 2.0, 0.4, Storage::Packed);
+                    board.set_boundary(boundary);
+                    board.set_lattice(lattice).unwrap();
+                    board.initialize();
+                    let cells: f64 = board.grid.hexes().filter(|hex| hex.value).map(|hex| board.get_energy(hex)).sum();
+                    assert_eq!(board.total_energy(), cells, "{} lattice of size {}", lattice.name(), size);
+                }
+            }
+        }
+    }
+
+    //both storages run the same chain from the same seed
+    #[test]
+    fn storages_give_the_same_run() {
+        let boards: Vec<HexBoard> = [Storage::Cells, Storage::Packed].into_iter().map(|storage| {
+            let mut board = HexBoard::with_storage(30, Generator::new(Backend::ChaCha12, 3, 0), 3.0, 1.0 / 3.0, storage);
+            board.set_species(vec![vec![1.0, 0.5], vec![0.5, 1.0]], vec![0.5, 0.5], 0.1);
+            board.initialize();
+            for _ in 0..100000 {
+                board.advance_timestep_repulsive();
+            }
+            return board;
+        }).collect();
+        assert!(boards[0].grid.hexes().zip(boards[1].grid.hexes()).all(|(a, b)| a.value == b.value && a.species == b.species));
+        assert_eq!(boards[0].accepted_jumps, boards[1].accepted_jumps);
+        assert_eq!(boards[0].total_energy(), boards[1].total_energy());
+    }
+}
@@ -1,77 +1,426 @@
-use rand::rngs::StdRng;
 use rand::Rng;
+use rand::seq::SliceRandom;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
+use crate::tracking::Tracker;
+use crate::configuration;
+use crate::substrate::{Realisation, Substrate};
+use crate::lattice::{self, Lattice};
+use crate::superstructure::Superstructure;
+use crate::grid::{Grid, Storage};
+use crate::random::Generator;
+use crate::error::{Error, Result};
+
+//what happens to neighbours that fall outside the board
+#[derive(Clone, Copy, PartialEq)]
+pub enum Boundary {
+    Periodic,
+    Open,
+}
+
+//named starting configurations, so hot and cold starts of the same parameters are one call apart
+#[derive(Clone, Copy, PartialEq)]
+pub enum InitialState {
+    //particles placed at random, a hot start
+    Random,
+    //particles packed on one sublattice, a cold start
+    Ordered(u8),
+    //three ordered stripes separated by domain walls
+    Domains,
+    //a dense slab surrounded by vacuum
+    Slab,
+    //an ordered disc of the given radius in a disordered background
+    Nucleus(f64),
+}
+
+impl fmt::Display for InitialState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            InitialState::Random => write!(f, "random start"),
+            InitialState::Ordered(sublattice) => write!(f, "cold start on sublattice {}", sublattice),
+            InitialState::Domains => write!(f, "three domain start"),
+            InitialState::Slab => write!(f, "slab start"),
+            InitialState::Nucleus(radius) => write!(f, "nucleus start of radius {:.1}", radius),
+        };
+    }
+}
 
 #[derive(Clone)]
 pub struct HexBoard {
     pub size: u16,
-    rng: StdRng,
-    disorder: f64,
-    pub grid: Vec<Vec<Hex>>,
+    pub(crate) rng: Generator,
+    pub(crate) disorder: f64,
+    //the cells, in the storage chosen at construction
+    pub grid: Grid,
     pub fillrate: f32,
+    pub coupling: f64,
+    pub field: f64,
+    pub boundary: Boundary,
+    pub tracker: Option<Tracker>,
+    pub steps: u64,
+    pub attempted_jumps: u64,
+    pub accepted_jumps: u64,
+    //interaction between every pair of species in units of coupling, a single species by default
+    pub interactions: Vec<Vec<f64>>,
+    //fraction of the particles of every species, used when the board is initialized
+    pub composition: Vec<f64>,
+    //probability that a step attempts to swap the species of two neighbouring particles instead of a jump
+    pub swap_probability: f64,
+    pub attempted_swaps: u64,
+    pub accepted_swaps: u64,
+    //blocked sites, pinned impurities and on-site energies of the substrate, none on a clean substrate
+    pub substrate: Option<Realisation>,
+    //geometry of the board, the triangular lattice by default
+    pub lattice: &'static dyn Lattice,
+    //the ordering measured by get_order on the triangular lattice, None picks the one closest to the fill
+    pub superstructure: Option<Superstructure>,
 }
 
 impl HexBoard {
-    pub fn new(size: u16, rng: StdRng, disorder: f64, fillrate: f32) -> Self {
-        let mut grid = vec![];
-        for x in 0..size {
+    pub fn new(size: u16, rng: Generator, disorder: f64, fillrate: f32) -> Self {
+        return HexBoard::with_storage(size, rng, disorder, fillrate, Storage::Cells);
+    }
 
-            let mut gridy: Vec<Hex> = vec![];
-            for y in 0..size {
-                gridy.push(Hex::new(x as i16, y as i16));
+    //a board whose cells are kept in storage, bit-packed storage takes a bit per cell instead of a Hex
+    pub fn with_storage(size: u16, rng: Generator, disorder: f64, fillrate: f32, storage: Storage) -> Self {
+        let grid = Grid::new(size, storage);
+        return HexBoard{size, rng, disorder, grid, fillrate, coupling: 1.0, field: 0.0, boundary: Boundary::Periodic, tracker: None, steps: 0, attempted_jumps: 0, accepted_jumps: 0,
+            interactions: vec![vec![1.0]], composition: vec![1.0], swap_probability: 0.0, attempted_swaps: 0, accepted_swaps: 0, substrate: None, lattice: &lattice::TRIANGULAR, superstructure: None}
+    }
+
+    //sets the nearest neighbour coupling, positive values are repulsive
+    pub fn set_coupling(&mut self, coupling: f64) {
+        self.coupling = coupling;
+    }
+
+    //sets a field that lowers the energy of particles on the first sublattice by field
+    pub fn set_field(&mut self, field: f64) {
+        self.field = field;
+    }
+
+    //moves the cells to another storage, keeping the configuration
+    pub fn set_storage(&mut self, storage: Storage) {
+        if storage != self.grid.storage() {
+            self.grid = self.grid.converted(storage);
+        }
+    }
+
+    pub fn storage(&self) -> Storage {
+        return self.grid.storage();
+    }
+
+    pub fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+    }
+
+    //chooses the ordering measured by get_order and used by the ordered initializers, None follows the fill
+    pub fn set_superstructure(&mut self, superstructure: Option<Superstructure>) {
+        self.superstructure = superstructure;
+    }
+
+    //puts the board on another lattice, before its substrate is set and it is initialized. fails on periodic boards of
+    //odd size for lattices that need an even one, so the boundary is set first
+    pub fn set_lattice(&mut self, lattice: &'static dyn Lattice) -> Result<()> {
+        if self.boundary == Boundary::Periodic && self.size % 2 == 1 && lattice.needs_even_size() {
+            return Err(Error::Parameter(format!("periodic {} boards need an even size, not {}", lattice.name(), self.size)));
+        }
+        self.lattice = lattice;
+        return Ok(());
+    }
+
+    //turns the board into a mixture of interactions.len() species. interactions[a][b] scales the coupling between
+    //neighbouring particles of species a and b and must be symmetric, composition is the fraction of each species
+    //given to the particles by the initializers and swap_probability the share of steps spent on species swaps
+    pub fn set_species(&mut self, interactions: Vec<Vec<f64>>, composition: Vec<f64>, swap_probability: f64) {
+        let count = interactions.len();
+        assert!(count > 0 && interactions.iter().all(|row| row.len() == count), "interactions must be a square matrix");
+        assert!((0..count).all(|a| (0..count).all(|b| interactions[a][b] == interactions[b][a])), "interactions must be symmetric");
+        assert_eq!(composition.len(), count, "composition needs one fraction per species");
+        let total: f64 = composition.iter().sum();
+        self.composition = composition.iter().map(|fraction| fraction / total).collect();
+        self.interactions = interactions;
+        self.swap_probability = swap_probability;
+    }
+
+    //puts the board on the realisation of substrate drawn from seed and places its impurities, replacing any previous substrate.
+    //call it before the initializers, which leave blocked sites and impurities alone
+    pub fn set_substrate(&mut self, substrate: Substrate, seed: u64) {
+        if let Some(previous) = self.substrate.take() {
+            for (x, column) in previous.sites.iter().enumerate() {
+                for (y, site) in column.iter().enumerate() {
+                    if site.pinned {
+                        self.set_cell(x as i16, y as i16, false);
+                    }
+                }
             }
-            grid.push(gridy);
         }
-        return HexBoard{size, rng, disorder, grid, fillrate}
+        if !substrate.is_clean() {
+            self.substrate = Some(substrate.realise(self.lattice, self.size, seed));
+            self.place_impurities();
+        }
+    }
+
+    //whether no particle can ever sit at x, y, because the substrate blocks it or it is not a site of the lattice
+    pub fn is_blocked(&self, x: i16, y: i16) -> bool {
+        return !self.lattice.is_site(x, y) || self.substrate.as_ref().is_some_and(|substrate| substrate.sites[x as usize][y as usize].blocked);
+    }
+
+    //whether x, y holds a pinned impurity
+    pub fn is_pinned(&self, x: i16, y: i16) -> bool {
+        return self.substrate.as_ref().is_some_and(|substrate| substrate.sites[x as usize][y as usize].pinned);
+    }
+
+    //whether hex holds a particle that can move, impurities are occupied cells but not particles
+    pub fn is_particle(&self, hex: Hex) -> bool {
+        return hex.value && !self.is_pinned(hex.x, hex.y);
+    }
+
+    //on-site energy of hex, 0 on a clean substrate
+    pub fn get_site_energy(&self, hex: Hex) -> f64 {
+        return self.substrate.as_ref().map_or(0.0, |substrate| substrate.sites[hex.x as usize][hex.y as usize].energy);
+    }
+
+    fn place_impurities(&mut self) {
+        let pinned: Vec<(i16, i16)> = self.cells(0..self.size, 0..self.size).into_iter().filter(|&(x, y)| self.is_pinned(x, y)).collect();
+        for (x, y) in pinned {
+            self.set_cell(x, y, true);
+            self.set_cell_species(x, y, 0);
+        }
+    }
+
+    pub fn species_count(&self) -> usize {
+        return self.interactions.len();
     }
 
     //get value at x, y
     pub fn get_cell(&self, x: i16, y:i16) -> Hex {
-        return self.grid[x as usize][y as usize];
+        return self.grid.get(x as usize, y as usize);
     }
 
     //set value at x, y
     pub fn set_cell(&mut self, x: i16, y: i16, value: bool) {
-        self.grid[x as usize][y as usize].value = value;
+        self.grid.set_value(x as usize, y as usize, value);
+    }
+
+    //set the species of the particle at x, y
+    pub fn set_cell_species(&mut self, x: i16, y: i16, species: u8) {
+        self.grid.set_species(x as usize, y as usize, species);
     }
 
 
     pub fn initialize(&mut self) {
-        for _ in 0..((self.size * self.size) as f32 * self.fillrate) as u16 {
+        for _ in 0..self.particles() {
             let mut c: bool = true;
             while c {
                 let x = self.rng.gen_range(0..self.size);
-                let y = self.rng.gen_range(0..self.size - 1);
+                let y = self.rng.gen_range(0..self.size);
                 let hex = self.get_cell(x as i16, y as i16);
-                if hex.value == false{
+                if !hex.value && !self.is_blocked(x as i16, y as i16) {
                     self.set_cell(x as i16, y as i16, true);
                     c = false;
                 }
             }
         }
+        self.assign_species();
+    }
+
+    //starts from one of the named configurations, replacing whatever is on the board
+    pub fn initialize_state(&mut self, state: InitialState) {
+        match state {
+            InitialState::Random => {
+                self.clear();
+                self.initialize();
+            }
+            InitialState::Ordered(sublattice) => self.initialize_ordered(sublattice),
+            InitialState::Domains => self.initialize_domains(),
+            InitialState::Slab => self.initialize_slab(),
+            InitialState::Nucleus(radius) => self.initialize_nucleus(radius),
+        }
+    }
+
+    //perfect order: particles fill the given sublattice of get_order first, then the following ones once it is full.
+    //the ordering only closes across the edges when size is a multiple of 6
+    pub fn initialize_ordered(&mut self, sublattice: u8) {
+        self.clear();
+        let cells = self.cells(0..self.size, 0..self.size);
+        let particles = self.particles();
+        self.pack(&cells, sublattice, particles);
+        self.assign_species();
+    }
+
+    //one vertical stripe ordered on every sublattice, three on the triangular lattice, so domain walls run along y,
+    //the last one across the x edge. the particles are shared evenly between the stripes
+    pub fn initialize_domains(&mut self) {
+        self.clear();
+        let particles = self.particles();
+        let stripes = self.get_sublattice_count() as u16;
+        for stripe in 0..stripes {
+            let start = self.size * stripe / stripes;
+            let end = self.size * (stripe + 1) / stripes;
+            let cells = self.cells(start..end, 0..self.size);
+            let share = particles / stripes as usize + if (stripe as usize) < particles % stripes as usize { 1 } else { 0 };
+            self.pack(&cells, stripe as u8, share);
+        }
+        self.assign_species();
+    }
+
+    //a fully occupied slab of rows in the middle of the board with vacuum above and below,
+    //the last row of the slab is only partially filled when the particles do not fill whole rows.
+    //blocked sites and impurities push the slab further down, wrapping to the top rows once it reaches the bottom
+    pub fn initialize_slab(&mut self) {
+        self.clear();
+        let particles = self.particles();
+        let rows = particles.div_ceil(self.size as usize);
+        let start = ((self.size as usize - rows) / 2) as u16;
+        let mut cells = self.cells(0..self.size, start..self.size);
+        cells.extend(self.cells(0..self.size, 0..start));
+        let mut placed = 0;
+        for (x, y) in cells {
+            if placed == particles {
+                break;
+            }
+            if !self.get_cell(x, y).value && !self.is_blocked(x, y) {
+                self.set_cell(x, y, true);
+                placed += 1;
+            }
+        }
+        self.assign_species();
+    }
+
+    //a disc of the given radius around the centre ordered on sublattice 0,
+    //with the remaining particles placed at random outside of it. when the free cells outside of the disc run out,
+    //as with a disc covering the whole board, the particles left over are not placed
+    pub fn initialize_nucleus(&mut self, radius: f64) {
+        self.clear();
+        let centre = self.get_position(self.size as i16 / 2, self.size as i16 / 2);
+        let (disc, outside): (Vec<_>, Vec<_>) = self.cells(0..self.size, 0..self.size).into_iter().partition(|&(x, y)| {
+            let position = self.get_position(x, y);
+            (position.0 - centre.0).powi(2) + (position.1 - centre.1).powi(2) <= radius * radius
+        });
+        let nucleus_sites = disc.iter().filter(|&&(x, y)| self.get_sublattice(x, y) == 0 && !self.get_cell(x, y).value && !self.is_blocked(x, y)).count();
+        let particles = self.particles();
+        let remaining = particles - self.pack(&disc, 0, nucleus_sites.min(particles));
+        let mut free: Vec<(i16, i16)> = outside.into_iter().filter(|&(x, y)| !self.get_cell(x, y).value && !self.is_blocked(x, y)).collect();
+        for _ in 0..remaining.min(free.len()) {
+            let (x, y) = free.swap_remove(self.rng.gen_range(0..free.len()));
+            self.set_cell(x, y, true);
+        }
+        self.assign_species();
+    }
+
+    //number of particles set by fillrate, a fraction of the sites of the lattice, rounded so a fillrate read back from
+    //a configuration gives the same count. impurities are not counted, and there are never more particles than free sites
+    fn particles(&self) -> usize {
+        let sites = self.lattice.site_count(self.size);
+        let free = self.cells(0..self.size, 0..self.size).into_iter().filter(|&(x, y)| !self.is_blocked(x, y) && !self.is_pinned(x, y)).count();
+        return ((sites as f32 * self.fillrate).round() as usize).min(free);
+    }
+
+    //empties the board, leaving the impurities of the substrate in place
+    pub(crate) fn clear(&mut self) {
+        self.grid.clear();
+        self.place_impurities();
+    }
+
+    //gives the particles on the board their species, in the proportions of composition and in random order.
+    //a board with a single species leaves the random stream untouched
+    fn assign_species(&mut self) {
+        if self.species_count() == 1 {
+            return;
+        }
+        let mut particles: Vec<(i16, i16)> = self.cells(0..self.size, 0..self.size).into_iter().filter(|&(x, y)| self.is_particle(self.get_cell(x, y))).collect();
+        particles.shuffle(&mut self.rng);
+        let mut cumulative = 0.0;
+        let mut start = 0;
+        for species in 0..self.species_count() {
+            cumulative += self.composition[species];
+            let end = ((cumulative * particles.len() as f64).round() as usize).min(particles.len());
+            for &(x, y) in &particles[start..end.max(start)] {
+                self.set_cell_species(x, y, species as u8);
+            }
+            start = end.max(start);
+        }
+    }
+
+    //coordinates of the cells in a block of the board, row by row
+    fn cells(&self, xs: std::ops::Range<u16>, ys: std::ops::Range<u16>) -> Vec<(i16, i16)> {
+        let mut cells = vec![];
+        for y in ys {
+            for x in xs.clone() {
+                cells.push((x as i16, y as i16));
+            }
+        }
+        return cells;
+    }
+
+    //places up to count particles on cells, first on the given sublattice and then on the following ones.
+    //returns how many were placed
+    fn pack(&mut self, cells: &[(i16, i16)], sublattice: u8, count: usize) -> usize {
+        let sublattices = self.get_sublattice_count() as u8;
+        let mut placed = 0;
+        for shift in 0..sublattices {
+            for &(x, y) in cells {
+                let free = !self.get_cell(x, y).value && !self.is_blocked(x, y);
+                if placed < count && free && self.get_sublattice(x, y) == (sublattice + shift) % sublattices {
+                    self.set_cell(x, y, true);
+                    placed += 1;
+                }
+            }
+        }
+        return placed;
+    }
+
+    //position of a cell in the plane, given by the lattice
+    pub fn get_position(&self, x: i16, y: i16) -> (f64, f64) {
+        return self.lattice.position(x, y);
+    }
+
+    //starts following the particles currently on the board, so their displacement can be measured
+    pub fn enable_tracking(&mut self) {
+        self.tracker = Some(Tracker::new(self));
+    }
+
+    //returns the neighbour offsets of the cell x, y, in the same order as get_neighbours
+    pub fn get_directions(&self, x: i16, y: i16) -> &'static [(i16, i16)] {
+        return self.lattice.directions(x, y);
+    }
+
+    //starts from the configuration saved in path (printfile csv, checkpoint or ascii map) instead of a random one
+    pub fn initialize_from_file(&mut self, path: &str) -> Result<()> {
+        configuration::load(self, path)?;
+        return Ok(());
     }
 
     pub fn get_neighbours(&self, x: i16, y: i16) -> Vec<Hex> {
-        let directions1 = vec![vec![1, 0], vec![-1, 0], vec![0, 1], vec![0, -1], vec![-1, 1], vec![-1, -1]];
-        let directions2 = vec![vec![1, 0], vec![-1, 0], vec![0, 1], vec![0, -1], vec![1, 1], vec![1, -1]];
         let mut n = vec![];
-        if y % 2 == 0 {
-            for dir in directions1 {
-                let newx = self.wrapx(x + dir[0]);
-                let newy = self.wrapy(y + dir[1]);
-                n.push(self.grid[newx as usize][newy as usize])
+        for dir in self.get_directions(x, y) {
+            if self.boundary == Boundary::Open && !self.is_inside(x + dir.0, y + dir.1) {
+                continue;
             }
+            let newx = self.wrapx(x + dir.0);
+            let newy = self.wrapy(y + dir.1);
+            n.push(self.get_cell(newx as i16, newy as i16))
         }
-        else {
-            for dir in directions2 {
-                let newx = self.wrapx(x + dir[0]);
-                let newy = self.wrapy(y + dir[1]);
-                n.push(self.grid[newx as usize][newy as usize])
+
+        return  n;
+    }
+
+    //the sites shell + 1 nearest neighbour steps away from x, y, see Lattice::shell
+    pub fn get_shell(&self, x: i16, y: i16, shell: usize) -> Vec<Hex> {
+        let mut sites = vec![];
+        for (dx, dy) in self.lattice.shell(x, y, shell) {
+            if self.boundary == Boundary::Open && !self.is_inside(x + dx, y + dy) {
+                continue;
             }
+            sites.push(self.get_cell(self.wrapx(x + dx) as i16, self.wrapy(y + dy) as i16));
         }
+        return sites;
+    }
 
-        return  n;
+    //whether x, y lies on the board without wrapping
+    pub fn is_inside(&self, x: i16, y: i16) -> bool {
+        return x >= 0 && y >= 0 && (x as u16) < self.size && (y as u16) < self.size;
     }
 
     pub fn wrapx(&self, index: i16) -> u16 {
@@ -93,160 +442,309 @@ impl HexBoard {
             return (self.size as i16 - 1) as u16;
         }
 
-        else if index as u16 >= self.size - 1 {
+        else if index as u16 >= self.size {
             return 0;
         }
 
         return index as u16;
     }
 
-    pub fn get_energy(&mut self, hex: Hex) -> u8 {
-        let mut count: u8 = 0;
-        let neighbours = self.get_neighbours(hex.x as i16, hex.y as i16);
-        for neighbour in  neighbours{
-            if neighbour.value == true {
-                count += 1;
-            }
-        }
-        return count;
+    //energy of the particle at hex with its neighbours in units of coupling, the number of occupied neighbours for a single species
+    pub fn get_energy(&self, hex: Hex) -> f64 {
+        return self.get_species_energy(hex, hex.species, None);
     }
 
     pub fn advance_timestep_repulsive(&mut self) {
+        self.steps += 1;
+        if self.swap_probability > 0.0 && self.rng.gen_bool(self.swap_probability) {
+            self.swap_species();
+            return;
+        }
         let rx = self.rng.gen_range(0..self.size);
         let ry = self.rng.gen_range(0..self.size);
         let hex = self.get_cell(rx as i16, ry as i16);
-        if hex.value == true {
+        if self.is_particle(hex) {
+            self.attempted_jumps += 1;
             let start_energy = self.get_energy(hex);
-            let ne = self.get_neighbours(rx as i16, ry as i16);
-            let i = self.rng.gen_range(0..ne.len());
-            let dest = ne[i];
-            if dest.value == false {
-                let end_energy = (self.get_energy(dest) as i16 - 1) as f64;
-                
-                if start_energy as f64 > end_energy {
-                    self.set_cell(rx as i16, ry as i16, false);
-                    self.set_cell(dest.x, dest.y, true);
-                }
-            
-                else if start_energy as f64 <= end_energy {
-                    let delta: f64 = (start_energy as f64 - end_energy) as f64;
-                    let check: bool = self.accept_change(delta);
-                    if check == true {
-                        self.set_cell(rx as i16, ry as i16, false);
-                        self.set_cell(dest.x, dest.y, true);
-                    }
+            let directions = self.get_directions(rx as i16, ry as i16);
+            let i = self.rng.gen_range(0..directions.len());
+            let (dx, dy) = directions[i];
+            //with open boundaries a jump off the board is rejected, so every direction keeps the same proposal probability
+            if self.boundary == Boundary::Open && !self.is_inside(rx as i16 + dx, ry as i16 + dy) {
+                return;
+            }
+            let dest = self.get_cell(self.wrapx(rx as i16 + dx) as i16, self.wrapy(ry as i16 + dy) as i16);
+            if !dest.value && !self.is_blocked(dest.x, dest.y) {
+                //the jumping particle is still a neighbour of dest, so its own bond is removed
+                let end_energy = self.get_species_energy(dest, hex.species, None) - self.interactions[hex.species as usize][hex.species as usize];
+                let delta: f64 = self.coupling * (end_energy - start_energy)
+                    + self.field * (self.get_field_weight(hex) - self.get_field_weight(dest))
+                    + self.get_site_energy(dest) - self.get_site_energy(hex);
+
+                if delta < 0.0 || self.accept_change(-delta) {
+                    self.jump(hex, dest, (dx, dy));
                 }
             }
         }
     }
 
+    //picks a particle and a neighbour of another species and exchanges their species with the metropolis rule.
+    //the field only depends on the sublattice, so only the interactions change
+    fn swap_species(&mut self) {
+        let rx = self.rng.gen_range(0..self.size) as i16;
+        let ry = self.rng.gen_range(0..self.size) as i16;
+        let hex = self.get_cell(rx, ry);
+        let directions = self.get_directions(rx, ry);
+        let (dx, dy) = directions[self.rng.gen_range(0..directions.len())];
+        if !self.is_particle(hex) || (self.boundary == Boundary::Open && !self.is_inside(rx + dx, ry + dy)) {
+            return;
+        }
+        let other = self.get_cell(self.wrapx(rx + dx) as i16, self.wrapy(ry + dy) as i16);
+        if !self.is_particle(other) || other.species == hex.species {
+            return;
+        }
+        self.attempted_swaps += 1;
+        //the bond between the two particles is the same before and after the swap
+        let before = self.get_species_energy(hex, hex.species, Some(other)) + self.get_species_energy(other, other.species, Some(hex));
+        let after = self.get_species_energy(hex, other.species, Some(other)) + self.get_species_energy(other, hex.species, Some(hex));
+        let delta = self.coupling * (after - before);
+        if delta < 0.0 || self.accept_change(-delta) {
+            self.set_cell_species(hex.x, hex.y, other.species);
+            self.set_cell_species(other.x, other.y, hex.species);
+            self.accepted_swaps += 1;
+        }
+    }
+
+    //interaction energy, in units of coupling, that a particle of species would have at hex, skipping the neighbour skip
+    pub fn get_species_energy(&self, hex: Hex, species: u8, skip: Option<Hex>) -> f64 {
+        let row = &self.interactions[species as usize];
+        let mut energy = 0.0;
+        for neighbour in self.get_neighbours(hex.x, hex.y) {
+            let skipped = skip.is_some_and(|skip| skip.x == neighbour.x && skip.y == neighbour.y);
+            if neighbour.value && !skipped {
+                energy += row[neighbour.species as usize];
+            }
+        }
+        return energy;
+    }
+
+    //1 on the sublattice of the lattice favoured by the field, 0 elsewhere. the superstructure of the fill is only measured,
+    //so the hamiltonian stays the same whatever the fill
+    pub(crate) fn get_field_weight(&self, hex: Hex) -> f64 {
+        if self.lattice.sublattice(hex.x, hex.y) == 0 {
+            return 1.0;
+        }
+        return 0.0;
+    }
+
+    //moves the particle in from to the free cell dest, reached along the neighbour offset
+    fn jump(&mut self, from: Hex, dest: Hex, offset: (i16, i16)) {
+        self.set_cell(from.x, from.y, false);
+        self.set_cell(dest.x, dest.y, true);
+        self.set_cell_species(dest.x, dest.y, from.species);
+        self.accepted_jumps += 1;
+        //the displacement is taken before wrapping, so hops across the periodic boundary are not lost
+        let start = self.get_position(from.x, from.y);
+        let end = self.get_position(from.x + offset.0, from.y + offset.1);
+        if let Some(tracker) = self.tracker.as_mut() {
+            tracker.hop((from.x as u16, from.y as u16), (dest.x as u16, dest.y as u16), (end.0 - start.0, end.1 - start.1));
+        }
+    }
+
+    //fraction of attempted particle jumps that were accepted
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.attempted_jumps == 0 {
+            return 0.0;
+        }
+        return self.accepted_jumps as f64 / self.attempted_jumps as f64;
+    }
+
     //returns the probability of a particle to jump to a neighbouring free cell
     pub fn accept_change(&mut self, delta_energy: f64) -> bool {
         let number: f64 = delta_energy * self.disorder;
         return self.rng.gen_bool(number.exp());
     }
 
-    //returns the total amount of energy in the system (counts multiple times neighbours)
-    pub fn total_energy(&self) -> u16 {
-        let mut count = 0;
-        for i in 0..self.grid.len() {
-            for j in 0..self.grid[i].len() {
-
-                if self.grid[i][j].value == true {
-                    let n = self.get_neighbours(i as i16, j as i16);
-                    // Check adjacent cells
-                    for hex in n {
-                        if hex.value == true {
-                            count += 1;
-                        }
-                    }
-                }
+    //returns the total amount of energy in the system in units of coupling (counts multiple times neighbours),
+    //every bond weighted by the interaction of the species it joins. impurities take part as particles of species 0,
+    //the on-site energies of the substrate are not included. a single species on bit-packed storage is counted a word at a time
+    pub fn total_energy(&self) -> f64 {
+        if let Grid::Packed(packed) = &self.grid {
+            if self.species_count() == 1 && self.lattice.parity_periodic() {
+                let pairs = packed.neighbour_pairs(|x, y| self.lattice.directions(x, y), self.boundary == Boundary::Periodic);
+                return self.interactions[0][0] * pairs as f64;
+            }
+        }
+        let mut count = 0.0;
+        for hex in self.grid.hexes() {
+            if hex.value {
+                count += self.get_energy(hex);
             }
         }
         return count;
     }
 
-    pub fn get_order(&self) -> Vec<f32> {
+    //the superstructure measured by get_order, None on lattices other than the triangular one
+    pub fn get_superstructure(&self) -> Option<Superstructure> {
+        return Superstructure::measured(self.lattice, self.fillrate as f64, self.superstructure);
+    }
 
-        let mut count_a: f32 = 0.0;
-        let mut count_b: f32 = 0.0;
-        let mut count_c: f32 = 0.0;
-        for i in 0..3 {
-            for x in 0..self.size {
-                for y in 0..self.size {
-                    let hex = self.get_cell(x as i16, y as i16);
-                    if y % 2 == 0{
-                        if i == 0 {
-                            if x % 3 == 0 && hex.value == true{
-                                count_a += 1.0;
-                            }
-                        }
-                        else if i == 1 {
-                            if (x as i16 - 1) % 3 == 0 && hex.value == true{
-                                count_b += 1.0;
-                            }
-                        }
-                        else if i == 2 {
-                            if (x as i16 - 2) % 3 == 0 && hex.value == true{
-                                count_c +=1.0;
-                            }
-                        }
-                    }
-                    else if (y - 1) % 2 == 0 {
-                        if i == 0 {
-                            if (x as i16 - 1) % 3 == 0 && hex.value == true{
-                                count_a += 1.0;
-                            }
-                        }
-                        else if i == 1 {
-                            if (x as i16 - 2) % 3 == 0 && hex.value == true{
-                                count_b += 1.0;
-                            }
-                        }
-                        else if i == 2 {
-                            if x % 3 == 0 && hex.value == true{
-                                count_c += 1.0;
-                            }
-                        }
-                    }
+    //returns which sublattice the cell at x, y belongs to, following get_order
+    pub fn get_sublattice(&self, x: i16, y: i16) -> u8 {
+        return self.sublattice_of(self.get_superstructure(), x, y);
+    }
+
+    fn sublattice_of(&self, superstructure: Option<Superstructure>, x: i16, y: i16) -> u8 {
+        return match superstructure {
+            Some(superstructure) => superstructure.sublattice(x, y),
+            None => self.lattice.sublattice(x, y),
+        };
+    }
+
+    //number of sublattices of get_order
+    pub fn get_sublattice_count(&self) -> usize {
+        return self.get_superstructure().map_or(self.lattice.sublattice_count(), |superstructure| superstructure.sublattice_count());
+    }
+
+    //nearest neighbour steps between the closest sites of a sublattice of get_order
+    pub fn get_sublattice_reach(&self) -> usize {
+        return self.get_superstructure().map_or(2, |superstructure| superstructure.reach());
+    }
+
+    //fractions of the particles on each sublattice of the measured superstructure, or of the lattice on lattices
+    //other than the triangular one. orderings seen on the vacancies give the fractions of the empty sites instead
+    pub fn get_order(&self) -> Vec<f32> {
+        let superstructure = self.get_superstructure();
+        let on_vacancies = superstructure.is_some_and(|superstructure| superstructure.on_vacancies());
+        let mut counts: Vec<f32> = vec![0.0; self.get_sublattice_count()];
+        let mut total: f32 = 0.0;
+        for x in 0..self.size as i16 {
+            for y in 0..self.size as i16 {
+                let hex = self.get_cell(x, y);
+                let counted = match on_vacancies {
+                    true => !hex.value && !self.is_blocked(x, y),
+                    false => self.is_particle(hex),
+                };
+                if counted {
+                    counts[self.sublattice_of(superstructure, x, y) as usize] += 1.0;
+                    total += 1.0;
                 }
             }
         }
-        let amount = ((self.size * self.size) as f32 * self.fillrate) as u16;
-        let order = vec![(count_a as f32 / amount as f32), (count_b / amount as f32), (count_c / amount as f32),];
+        //divided by what was counted rather than by the fill, which the rounding of the initializers and the substrate can miss
+        if total == 0.0 {
+            return counts;
+        }
+        let order = counts.into_iter().map(|count| count / total).collect();
 
         return order;
     }
 
 
-    pub fn printfile(&mut self, filename: &str) {
+    //fractions of the particles of one species on each sublattice of get_order,
+    //get_order counts the particles of every species together
+    pub fn get_species_order(&self, species: u8) -> Vec<f32> {
+        let superstructure = self.get_superstructure();
+        let mut counts = vec![0.0; self.get_sublattice_count()];
+        let mut total = 0.0;
+        for x in 0..self.size as i16 {
+            for y in 0..self.size as i16 {
+                let hex = self.get_cell(x, y);
+                if self.is_particle(hex) && hex.species == species {
+                    counts[self.sublattice_of(superstructure, x, y) as usize] += 1.0;
+                    total += 1.0;
+                }
+            }
+        }
+        if total == 0.0 {
+            return counts;
+        }
+        return counts.into_iter().map(|count: f32| count / total).collect();
+    }
+
+    //magnitude of the complex order parameter psi = sum_k n_k exp(2 pi i k / K) built from the K fractions of get_order,
+    //1 for a perfect ordering on one sublattice, as the sqrt(3) x sqrt(3) one, and close to 0 in the disordered phase
+    pub fn get_order_parameter(&self) -> f64 {
+        let orders = self.get_order();
+        let mut real = 0.0;
+        let mut imaginary = 0.0;
+        for (k, &order) in orders.iter().enumerate() {
+            let angle = 2.0 * std::f64::consts::PI * k as f64 / orders.len() as f64;
+            real += order as f64 * angle.cos();
+            imaginary += order as f64 * angle.sin();
+        }
+        return (real * real + imaginary * imaginary).sqrt();
+    }
+
+    //writes the particles as csv rows of x, y with odd rows shifted by half a cell,
+    //mixtures get a third column with the species, left empty on the corner markers.
+    //impurities belong to the substrate and are not written. the first line is a comment with the random generator of the board
+    pub fn printfile(&mut self, filename: &str) -> Result<()> {
         let mut xdata: Vec<u16> = vec![];
         let mut ydata: Vec<u16> = vec![];
         for x in 0..self.size {
             for y in 0..self.size {
-                if self.get_cell(x as i16, y as i16).value == true {
+                if self.is_particle(self.get_cell(x as i16, y as i16)) {
                     xdata.push(x);
                     ydata.push(y);
                 }
             }
         }
+        let mixture = self.species_count() > 1;
+        let marker_end = if mixture { ",\n" } else { "\n" };
         // Open the file for writing
-        let mut file = File::create(filename).expect("Failed to create file");
+        let mut file = File::create(filename)?;
+        writeln!(file, "# {} at step {}", self.rng, self.steps)?;
         // Write the CSV headers
-        file.write_all(b"x,y\n").expect("Failed to write headers");
-        let s1 = format!("0,{}\n", self.size);
-        let s2 = format!("{},0\n", self.size);
-        file.write_all(s1.as_bytes()).expect("Failed to write headers");
-        file.write_all(s2.as_bytes()).expect("Failed to write headers");
-        file.write_all(b"0,0\n").expect("Failed to write headers");
+        file.write_all(if mixture { b"x,y,species\n".as_slice() } else { b"x,y\n".as_slice() })?;
+        let s1 = format!("0,{}{}", self.size, marker_end);
+        let s2 = format!("{},0{}", self.size, marker_end);
+        file.write_all(s1.as_bytes())?;
+        file.write_all(s2.as_bytes())?;
+        file.write_all(format!("0,0{}", marker_end).as_bytes())?;
         // Write the data rows
-        for (_, (&x, &y)) in xdata.iter().zip(ydata.iter()).enumerate() {
+        for (&x, &y) in xdata.iter().zip(ydata.iter()) {
             let shifted_x = if y % 2 == 1 { x as f32 + 0.5 } else { x as f32 };
-            let row = format!("{},{}\n", shifted_x, y);
-            file.write_all(row.as_bytes()).expect("Failed to write row");
+            let row = if mixture {
+                format!("{},{},{}\n", shifted_x, y, self.get_cell(x as i16, y as i16).species)
+            } else {
+                format!("{},{}\n", shifted_x, y)
+            };
+            file.write_all(row.as_bytes())?;
+        }
+
+        file.flush()?;
+        return Ok(());
+    }
+
+    //writes the board as an ascii map that initialize_from_file can read back, odd rows are indented by one space.
+    //particles are 'o', or 'A', 'B', ... after their species in a mixture, blocked sites 'x' and impurities '*'
+    //the first line is a comment with the random generator of the board
+    pub fn printmap(&self, filename: &str) -> Result<()> {
+        let mut file = File::create(filename)?;
+        writeln!(file, "# {} at step {}", self.rng, self.steps)?;
+        for y in 0..self.size {
+            let mut row = String::new();
+            if y % 2 == 1 {
+                row.push(' ');
+            }
+            for x in 0..self.size {
+                let hex = self.get_cell(x as i16, y as i16);
+                let cell = match hex.value {
+                    false if self.is_blocked(hex.x, hex.y) => 'x',
+                    false => '.',
+                    true if self.is_pinned(hex.x, hex.y) => '*',
+                    true if self.species_count() > 1 => (b'A' + hex.species) as char,
+                    true => 'o',
+                };
+                row.push(cell);
+                row.push(' ');
+            }
+            row.push('\n');
+            file.write_all(row.as_bytes())?;
         }
-    
-        file.flush().expect("Failed to flush file");
+        file.flush()?;
+        return Ok(());
     }
 
 }
@@ -256,12 +754,256 @@ pub struct Hex {
     pub x: i16,
     pub y: i16,
     pub value: bool,
+    //species of the particle, only meaningful when value is set
+    pub species: u8,
 }
 
 impl Hex {
     //costruttore
     pub fn new(x: i16, y: i16) -> Self {
         let value: bool = false;
-        return Hex{x, y, value}
+        return Hex{x, y, value, species: 0}
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exact;
+    use crate::substrate::SiteEnergies;
+    use crate::random::Backend;
+    use crate::sweep::Parameters;
+    use std::collections::HashMap;
+
+    //the occupied cells of a board with their species
+    type Configuration = Vec<(i16, i16, u8)>;
+
+    fn boards() -> Vec<HexBoard> {
+        let mut boards = vec![];
+        for lattice in lattice::builtin() {
+            for boundary in [Boundary::Periodic, Boundary::Open] {
+                for size in [4, 6, 8] {
+                    let mut board = HexBoard::new(size, Generator::new(Backend::ChaCha12, size as u64, 0), 2.0, 1.0 / 3.0);
+                    board.set_boundary(boundary);
+                    board.set_lattice(lattice).unwrap();
+                    boards.push(board);
+                }
+            }
+        }
+        return boards;
+    }
+
+    //mobile particles of every species and the impurities of the board
+    fn census(board: &HexBoard) -> (Vec<usize>, Vec<(i16, i16)>) {
+        let mut species = vec![0; board.species_count()];
+        let mut impurities = vec![];
+        for hex in board.grid.hexes() {
+            if board.is_particle(hex) {
+                species[hex.species as usize] += 1;
+            }
+            else if hex.value {
+                impurities.push((hex.x, hex.y));
+            }
+        }
+        return (species, impurities);
+    }
+
+    #[test]
+    fn moves_conserve_particles() {
+        for mut board in boards() {
+            board.set_species(vec![vec![1.0, 0.5], vec![0.5, 1.0]], vec![0.5, 0.5], 0.3);
+            board.set_substrate(Substrate{blocked: 0.1, impurities: 0.1, site_energies: SiteEnergies::Gaussian(0.5)}, 17);
+            board.initialize_state(InitialState::Random);
+            let before = census(&board);
+            for _ in 0..20000 {
+                board.advance_timestep_repulsive();
+                assert!(census(&board) == before, "{} board lost or gained particles", board.lattice.name());
+            }
+            assert!(board.accepted_jumps > 0 && board.accepted_swaps > 0);
+        }
+    }
+
+    #[test]
+    fn total_energy_sums_particle_energies() {
+        for mut board in boards() {
+            board.set_species(vec![vec![1.0, 0.3], vec![0.3, 2.0]], vec![0.5, 0.5], 0.0);
+            board.initialize_state(InitialState::Random);
+            let mut sum = 0.0;
+            let mut bonds = 0.0;
+            for hex in board.grid.hexes() {
+                if hex.value {
+                    sum += board.get_energy(hex);
+                    for neighbour in board.get_neighbours(hex.x, hex.y) {
+                        if neighbour.value && (neighbour.x, neighbour.y) > (hex.x, hex.y) {
+                            bonds += board.interactions[hex.species as usize][neighbour.species as usize];
+                        }
+                    }
+                }
+            }
+            assert!((board.total_energy() - sum).abs() < 1e-9);
+            //every bond is seen from both of its ends
+            assert!((board.total_energy() - 2.0 * bonds).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn neighbours_are_symmetric() {
+        for board in boards() {
+            for x in 0..board.size as i16 {
+                for y in 0..board.size as i16 {
+                    if board.is_blocked(x, y) {
+                        continue;
+                    }
+                    for neighbour in board.get_neighbours(x, y) {
+                        assert!(!board.is_blocked(neighbour.x, neighbour.y), "{} site {}, {} has a neighbour off the lattice", board.lattice.name(), x, y);
+                        let back = board.get_neighbours(neighbour.x, neighbour.y);
+                        assert!(back.iter().any(|hex| hex.x == x && hex.y == y),
+                            "{} lattice of size {}: {}, {} is a neighbour of {}, {} but not the other way", board.lattice.name(), board.size, neighbour.x, neighbour.y, x, y);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn order_fractions_sum_to_one() {
+        for mut board in boards() {
+            for fill in [0.1, 1.0 / 7.0, 0.25, 1.0 / 3.0, 0.5, 2.0 / 3.0, 0.8] {
+                board.fillrate = fill;
+                board.set_substrate(Substrate{blocked: 0.05, impurities: 0.05, site_energies: SiteEnergies::Flat}, 3);
+                board.initialize_state(InitialState::Random);
+                let sum: f32 = board.get_order().iter().sum();
+                assert!((sum - 1.0).abs() < 1e-5, "{} lattice of size {} at fill {}: fractions sum to {}", board.lattice.name(), board.size, fill, sum);
+            }
+        }
+    }
+
+    #[test]
+    fn csv_files_name_their_generator_and_read_back() {
+        let mut board = HexBoard::new(6, Generator::new(Backend::Pcg64, 21, 3), 1.0, 1.0 / 3.0);
+        board.initialize();
+        let path = std::env::temp_dir().join(format!("tesina_mfs_printfile_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        board.printfile(path).unwrap();
+        let first = std::fs::read_to_string(path).unwrap().lines().next().unwrap().to_string();
+        let mut loaded = HexBoard::new(6, Generator::new(Backend::ChaCha12, 1, 0), 1.0, 0.5);
+        loaded.initialize_from_file(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(first, "# pcg64 seed 21 stream 3 at step 0");
+        assert!(board.grid.hexes().zip(loaded.grid.hexes()).all(|(a, b)| a.value == b.value));
+    }
+
+    #[test]
+    fn substrates_only_cover_lattice_sites() {
+        for mut board in boards() {
+            board.set_substrate(Substrate{blocked: 0.25, impurities: 0.25, site_energies: SiteEnergies::Flat}, 12);
+            let sites = board.lattice.site_count(board.size);
+            let realisation = board.substrate.as_ref().unwrap();
+            let mut counts = (0, 0);
+            for x in 0..board.size as i16 {
+                for y in 0..board.size as i16 {
+                    let site = realisation.sites[x as usize][y as usize];
+                    assert!(board.lattice.is_site(x, y) || !(site.blocked || site.pinned), "{} cell {}, {} is not a site", board.lattice.name(), x, y);
+                    counts.0 += site.blocked as usize;
+                    counts.1 += site.pinned as usize;
+                }
+            }
+            let expected = (sites as f64 * 0.25).round() as usize;
+            assert_eq!(counts, (expected, expected), "{} lattice of size {}", board.lattice.name(), board.size);
+        }
+    }
+
+    #[test]
+    fn structured_starts_fit_on_the_board() {
+        //a nucleus wider than the board leaves no cells outside of it, so only sublattice 0 is filled
+        let mut board = HexBoard::new(12, Generator::new(Backend::ChaCha12, 4, 0), 1.0, 0.5);
+        board.initialize_state(InitialState::Nucleus(100.0));
+        let particles: Vec<Hex> = board.grid.hexes().filter(|&hex| board.is_particle(hex)).collect();
+        assert_eq!(particles.len(), 48);
+        assert!(particles.iter().all(|hex| board.get_sublattice(hex.x, hex.y) == 0));
+
+        //the slab of a large dense board holds more particles than fit in a u16 and wraps around the blocked sites
+        let mut board = HexBoard::new(300, Generator::new(Backend::ChaCha12, 5, 0), 1.0, 0.9);
+        board.set_substrate(Substrate{blocked: 0.05, ..Substrate::clean()}, 6);
+        board.initialize_state(InitialState::Slab);
+        assert_eq!(census(&board).0.iter().sum::<usize>(), board.particles());
+    }
+
+    //runs a chain with jumps and swaps on a tiny open board and counts the transitions between configurations. with detailed balance
+    //the rates of every pair of configurations satisfy P(a -> b) / P(b -> a) = exp(-betaj (E(b) - E(a)))
+    #[test]
+    fn transitions_satisfy_detailed_balance() {
+        let mut board = HexBoard::new(3, Generator::new(Backend::ChaCha12, 99, 0), 1.0, 3.0 / 9.0);
+        board.set_boundary(Boundary::Open);
+        board.set_species(vec![vec![1.0, -0.5], vec![-0.5, 2.0]], vec![0.5, 0.5], 0.5);
+        board.set_field(0.5);
+        board.set_substrate(Substrate{site_energies: SiteEnergies::Uniform(1.0), ..Substrate::clean()}, 8);
+        board.initialize();
+        let key = |board: &HexBoard| -> Configuration {
+            return board.grid.hexes().filter(|hex| hex.value).map(|hex| (hex.x, hex.y, hex.species)).collect();
+        };
+        let mut energies: HashMap<Configuration, f64> = HashMap::new();
+        let mut visits: HashMap<Configuration, f64> = HashMap::new();
+        let mut transitions: HashMap<(Configuration, Configuration), f64> = HashMap::new();
+        let mut state = key(&board);
+        energies.insert(state.clone(), exact::energy(&board));
+        for _ in 0..3000000 {
+            board.advance_timestep_repulsive();
+            *visits.entry(state.clone()).or_insert(0.0) += 1.0;
+            let next = key(&board);
+            if next != state {
+                energies.entry(next.clone()).or_insert_with(|| exact::energy(&board));
+                *transitions.entry((state, next.clone())).or_insert(0.0) += 1.0;
+            }
+            state = next;
+        }
+        let mut checked = 0;
+        for ((a, b), &forward) in &transitions {
+            let backward = transitions.get(&(b.clone(), a.clone())).cloned().unwrap_or(0.0);
+            if forward < 20.0 || backward < 20.0 {
+                continue;
+            }
+            let ratio = (forward / visits[a]) / (backward / visits[b]);
+            let expected = -board.disorder * (energies[b] - energies[a]);
+            let error = (1.0 / forward + 1.0 / backward).sqrt();
+            assert!((ratio.ln() - expected).abs() < 4.0 * error, "ln rate ratio {:.4}, expected {:.4} +- {:.4}", ratio.ln(), expected, error);
+            checked += 1;
+        }
+        assert!(checked > 100, "only {} pairs of configurations were visited often enough", checked);
+    }
+
+    //wrapy used to wrap row size - 1 to row 0 as well as row size, so the last row of a periodic board was never reached
+    #[test]
+    fn wrapping_keeps_the_edge_rows() {
+        let board = HexBoard::new(6, Generator::new(Backend::ChaCha12, 1, 0), 1.0, 1.0 / 3.0);
+        assert_eq!((0..8).map(|y| board.wrapy(y - 1)).collect::<Vec<u16>>(), vec![5, 0, 1, 2, 3, 4, 5, 0]);
+        assert_eq!((0..8).map(|x| board.wrapx(x - 1)).collect::<Vec<u16>>(), vec![5, 0, 1, 2, 3, 4, 5, 0]);
+    }
+
+    //the field acts on sublattice 0 of the lattice, whichever superstructure the fill makes get_order measure
+    #[test]
+    fn field_energy_does_not_depend_on_the_fill() {
+        let weights = |fill: f32| -> Vec<f64> {
+            let board = HexBoard::new(12, Generator::new(Backend::ChaCha12, 1, 0), 1.0, fill);
+            return board.grid.hexes().map(|hex| board.get_field_weight(hex)).collect();
+        };
+        let expected = weights(1.0 / 3.0);
+        for fill in [0.25, 1.0 / 7.0, 2.0 / 3.0, 0.5] {
+            assert_eq!(weights(fill), expected, "fill {}", fill);
+        }
+        assert_eq!(expected.iter().sum::<f64>(), 48.0);
+    }
+
+    //the wrapped edges of the lattices whose neighbours follow the parity of the rows or columns only match at even sizes
+    #[test]
+    fn odd_periodic_boards_need_the_square_lattice() {
+        for lattice in lattice::builtin() {
+            let mut board = HexBoard::new(5, Generator::new(Backend::ChaCha12, 1, 0), 1.0, 0.2);
+            assert_eq!(board.set_lattice(lattice).is_ok(), lattice.name() == lattice::SQUARE.name(), "{} lattice", lattice.name());
+            board.set_boundary(Boundary::Open);
+            assert!(board.set_lattice(lattice).is_ok());
+        }
+        assert!(Parameters::new(7, 1.0 / 3.0, 1.0).build(Generator::new(Backend::ChaCha12, 1, 0)).is_err());
+        assert!(Parameters{boundary: Boundary::Open, ..Parameters::new(7, 1.0 / 3.0, 1.0)}.build(Generator::new(Backend::ChaCha12, 1, 0)).is_ok());
+    }
+}
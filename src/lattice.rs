@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+
+//geometry of a board. every lattice lives on the size x size grid of cells of the board, cells that are not sites
+//of the lattice are never occupied. neighbours are given as offsets, which may depend on the cell, and are wrapped
+//or cut by the board according to its boundary
+pub trait Lattice: Sync {
+    fn name(&self) -> &'static str;
+
+    //offsets from the site x, y to its nearest neighbours
+    fn directions(&self, x: i16, y: i16) -> &'static [(i16, i16)];
+
+    //position of the cell x, y in the plane, nearest neighbours are at distance 1
+    fn position(&self, x: i16, y: i16) -> (f64, f64);
+
+    //number of sublattices of the ordering measured by get_order
+    fn sublattice_count(&self) -> usize;
+
+    //sublattice of the site x, y, from 0 to sublattice_count - 1
+    fn sublattice(&self, x: i16, y: i16) -> u8;
+
+    //whether the cell x, y is a site, every cell is one unless the lattice leaves some out
+    fn is_site(&self, _x: i16, _y: i16) -> bool {
+        return true;
+    }
+
+    //whether the neighbour offsets of a site only depend on the parities of x and y and move by at most one cell along x,
+    //which lets bit-packed boards count the neighbours of a whole row at once
+    fn parity_periodic(&self) -> bool {
+        return false;
+    }
+
+    //whether the neighbours of a site depend on the parity of its row or column, so that a periodic board only closes
+    //on itself when its size is even
+    fn needs_even_size(&self) -> bool {
+        return false;
+    }
+
+    //number of sites on a size x size grid
+    fn site_count(&self, size: u16) -> usize {
+        return (0..size as i16).map(|x| (0..size as i16).filter(|&y| self.is_site(x, y)).count()).sum();
+    }
+
+    //offsets to the sites exactly shell + 1 nearest neighbour steps away from x, y: shell 0 holds the nearest
+    //neighbours, shell 1 the sites two steps away, and so on
+    fn shell(&self, x: i16, y: i16, shell: usize) -> Vec<(i16, i16)> {
+        let mut seen: HashSet<(i16, i16)> = HashSet::from([(0, 0)]);
+        let mut front = vec![(0, 0)];
+        for _ in 0..=shell {
+            let mut next = vec![];
+            for (dx, dy) in front {
+                for &(ex, ey) in self.directions(x + dx, y + dy) {
+                    let site = (dx + ex, dy + ey);
+                    if seen.insert(site) {
+                        next.push(site);
+                    }
+                }
+            }
+            front = next;
+        }
+        return front;
+    }
+}
+
+//the six neighbour lattice of the original board, stored as rows with every odd row shifted by half a cell.
+//the three sublattices of the sqrt(3) x sqrt(3) ordering close for sizes that are a multiple of 6
+pub struct Triangular;
+
+//a square grid with the vertical bonds of every other site removed, which is a honeycomb lattice drawn as a brick wall.
+//its two sublattices are the two sites of the unit cell
+pub struct Honeycomb;
+
+//four neighbours, with the two sublattices of the checkerboard ordering at fill 1/2
+pub struct Square;
+
+//a triangular lattice with a quarter of its sites removed, stored on sheared axes where the cells with odd x and odd y
+//are not sites. its three sublattices are the three sites of the unit cell
+pub struct Kagome;
+
+pub static TRIANGULAR: Triangular = Triangular;
+pub static HONEYCOMB: Honeycomb = Honeycomb;
+pub static SQUARE: Square = Square;
+pub static KAGOME: Kagome = Kagome;
+
+//the lattices provided by the crate, as stored in checkpoints
+pub fn builtin() -> [&'static dyn Lattice; 4] {
+    return [&TRIANGULAR, &HONEYCOMB, &SQUARE, &KAGOME];
+}
+
+pub fn by_name(name: &str) -> Option<&'static dyn Lattice> {
+    return builtin().into_iter().find(|lattice| lattice.name() == name);
+}
+
+impl Lattice for Triangular {
+    fn name(&self) -> &'static str {
+        return "triangular";
+    }
+
+    fn directions(&self, _x: i16, y: i16) -> &'static [(i16, i16)] {
+        if y % 2 == 0 {
+            return &[(1, 0), (-1, 0), (0, 1), (0, -1), (-1, 1), (-1, -1)];
+        }
+        return &[(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1)];
+    }
+
+    fn position(&self, x: i16, y: i16) -> (f64, f64) {
+        return (x as f64 + 0.5 * y.rem_euclid(2) as f64, y as f64 * 3f64.sqrt() / 2.0);
+    }
+
+    fn sublattice_count(&self) -> usize {
+        return 3;
+    }
+
+    fn sublattice(&self, x: i16, y: i16) -> u8 {
+        if y % 2 == 0 {
+            return (x % 3) as u8;
+        }
+        return ((x + 2) % 3) as u8;
+    }
+
+    fn parity_periodic(&self) -> bool {
+        return true;
+    }
+
+    fn needs_even_size(&self) -> bool {
+        return true;
+    }
+}
+
+impl Lattice for Honeycomb {
+    fn name(&self) -> &'static str {
+        return "honeycomb";
+    }
+
+    //every site keeps its two horizontal bonds and one vertical bond, up on one sublattice and down on the other
+    fn directions(&self, x: i16, y: i16) -> &'static [(i16, i16)] {
+        if (x + y).rem_euclid(2) == 0 {
+            return &[(1, 0), (-1, 0), (0, 1)];
+        }
+        return &[(1, 0), (-1, 0), (0, -1)];
+    }
+
+    //the rows are zigzag chains of the honeycomb, the sites bonded upwards a quarter of a bond above the row
+    //and the others a quarter of a bond below it
+    fn position(&self, x: i16, y: i16) -> (f64, f64) {
+        let shift = if (x + y).rem_euclid(2) == 0 { 0.25 } else { -0.25 };
+        return (x as f64 * 3f64.sqrt() / 2.0, y as f64 * 1.5 + shift);
+    }
+
+    fn sublattice_count(&self) -> usize {
+        return 2;
+    }
+
+    fn sublattice(&self, x: i16, y: i16) -> u8 {
+        return (x + y).rem_euclid(2) as u8;
+    }
+
+    fn parity_periodic(&self) -> bool {
+        return true;
+    }
+
+    fn needs_even_size(&self) -> bool {
+        return true;
+    }
+}
+
+impl Lattice for Square {
+    fn name(&self) -> &'static str {
+        return "square";
+    }
+
+    fn directions(&self, _x: i16, _y: i16) -> &'static [(i16, i16)] {
+        return &[(1, 0), (-1, 0), (0, 1), (0, -1)];
+    }
+
+    fn position(&self, x: i16, y: i16) -> (f64, f64) {
+        return (x as f64, y as f64);
+    }
+
+    fn sublattice_count(&self) -> usize {
+        return 2;
+    }
+
+    fn sublattice(&self, x: i16, y: i16) -> u8 {
+        return (x + y).rem_euclid(2) as u8;
+    }
+
+    fn parity_periodic(&self) -> bool {
+        return true;
+    }
+}
+
+impl Lattice for Kagome {
+    fn name(&self) -> &'static str {
+        return "kagome";
+    }
+
+    //the triangular neighbours (±1, 0), (0, ±1), (1, -1) and (-1, 1) without the ones landing on removed cells
+    fn directions(&self, x: i16, y: i16) -> &'static [(i16, i16)] {
+        return match (x.rem_euclid(2), y.rem_euclid(2)) {
+            (0, 0) => &[(1, 0), (-1, 0), (0, 1), (0, -1)],
+            (1, 0) => &[(1, 0), (-1, 0), (1, -1), (-1, 1)],
+            _ => &[(0, 1), (0, -1), (1, -1), (-1, 1)],
+        };
+    }
+
+    fn position(&self, x: i16, y: i16) -> (f64, f64) {
+        return (x as f64 + 0.5 * y as f64, y as f64 * 3f64.sqrt() / 2.0);
+    }
+
+    fn sublattice_count(&self) -> usize {
+        return 3;
+    }
+
+    fn sublattice(&self, x: i16, y: i16) -> u8 {
+        return match (x.rem_euclid(2), y.rem_euclid(2)) {
+            (0, 0) => 0,
+            (1, 0) => 1,
+            _ => 2,
+        };
+    }
+
+    fn is_site(&self, x: i16, y: i16) -> bool {
+        return x.rem_euclid(2) == 0 || y.rem_euclid(2) == 0;
+    }
+
+    fn parity_periodic(&self) -> bool {
+        return true;
+    }
+
+    fn needs_even_size(&self) -> bool {
+        return true;
+    }
+}
@@ -1,60 +1,323 @@
 mod plot;
 mod batchrun;
 mod hexboard;
+mod domains;
+mod tracking;
+mod statistics;
+mod equilibration;
+mod sweep;
+mod phase;
+mod checkpoint;
+mod configuration;
+mod series;
+mod error;
+mod observer;
+mod substrate;
+mod lattice;
+mod superstructure;
+mod exact;
+mod bench;
+mod parallel;
+mod grid;
+mod random;
 
-use hexboard::HexBoard;
-use rand::rngs::StdRng;
-use rand::SeedableRng;
+use hexboard::{HexBoard, InitialState};
+use domains::Domains;
 use std::env;
+use std::thread;
+use std::fs;
+use std::time::Instant;
 use plot::Plot;
+use statistics::{ErrorMethod, Sampling};
+use equilibration::{Criterion, Equilibration};
+use sweep::{Parameter, Parameters, Sweep, Values};
+use series::{Format, Timeseries};
+use observer::{Observer, Snapshots};
+use substrate::{SiteEnergies, Substrate};
+use grid::Storage;
+use random::{Backend, Generator};
+use error::report;
 use plotters::prelude::*;
 
 fn main() {
     
     env::set_var("RUST_BACKTRACE", "1");
 
+    //cargo run --release -- bench measures the kernels instead of simulating, appending the results to benchmarks.csv
+    if env::args().nth(1).as_deref() == Some("bench") {
+        report("benchmarks.csv", bench::run("benchmarks.csv"));
+        return;
+    }
+
     //set sim parameters
-    let rng = StdRng::seed_from_u64(123067890);
+    let rng = Generator::new(Backend::ChaCha12, 123067890, 0);
 
-    let mut board = HexBoard::new(30, rng, 5 as f64, 1.0/3.0);
-    board.initialize();
-    //run simulation
-    let mut energy: Vec<(i32, f64)> = vec![];
-    let mut order: Vec<Vec<(i32, f64)>> = vec![vec![], vec![], vec![]];
+    //cargo run --release -- resume continues an interrupted run from the board saved to board.ckpt. only the board is
+    //checkpointed, so the recorded series and the equilibration criteria start again empty from the resumed step
+    let mut board = match env::args().nth(1).as_deref() {
+        Some("resume") => {
+            let Some(board) = report("could not resume from board.ckpt", checkpoint::load("board.ckpt")) else {
+                std::process::exit(1);
+            };
+            println!("> Resuming from board.ckpt at step {}, the series restart from there", board.steps);
+            board
+        }
+        //an initial configuration can be given as the first argument, otherwise particles are placed at random
+        argument => {
+            let mut board = HexBoard::new(30, rng, 5.0, 1.0/3.0);
+            match argument {
+                Some(path) => {
+                    if report(&format!("could not load {}", path), board.initialize_from_file(path)).is_none() {
+                        std::process::exit(1);
+                    }
+                }
+                None => board.initialize(),
+            }
+            board
+        }
+    };
+    //run simulation, recording the observables with observers
+    let mut energy = observer::energy(1);
+    let mut order = observer::order(1);
+    let mut psi = observer::psi(1);
+    let mut domain_series = observer::domains(1);
+    let mut snapshots = report("snapshots", Snapshots::new("snapshots", 1000));
+    let sweep = board.size as i32 * board.size as i32;
+    //stop once the board orders, or after a maximum number of steps in disordered phases
+    let Some(mut equilibration) = report("burn-in", Equilibration::new(vec![Criterion::OrderThreshold(0.7), Criterion::MaxSteps(20000000)])) else {
+        std::process::exit(1);
+    };
+    let mut observers: Vec<&mut dyn Observer> = vec![&mut energy, &mut order, &mut psi, &mut domain_series];
+    if let Some(snapshots) = snapshots.as_mut() {
+        observers.push(snapshots);
+    }
     let mut c = true;
-    let mut x = 0;
-    while c == true {
+    let mut x = board.steps as i32;
+    while c {
         board.advance_timestep_repulsive();
-        energy.push((x, board.total_energy() as f64));
-        let orders = board.get_order();
-        order[0].push((x, orders[0] as f64));
-        order[1].push((x, orders[1] as f64));
-        order[2].push((x, orders[2] as f64));
+        observer::observe(&mut observers, &board, x);
         x += 1;
-        if orders[0] > 0.7 || orders[1] > 0.7 || orders[2] > 0.7 {
+        if x % (100 * sweep) == 0 {
+            report("could not write board.ckpt", checkpoint::save(&board, "board.ckpt"));
+        }
+        if let Some(reason) = equilibration.check(&board, x) {
+            println!("> Stopped: {}", reason);
             c = false;
         }
     }
+    observer::finish(&mut observers, &board);
+    let _ = fs::remove_file("board.ckpt");
+    let energy = energy.first().to_vec();
+    let order = order.series;
+    let psi = psi.first().to_vec();
+    let domain_size = domain_series.first().to_vec();
+    let domain_count = domain_series.series.get(1).cloned().unwrap_or_default();
 
     //output data
     let energy_plot = Plot::new(500, 500, 0..energy.len() as i32, 0.0..energy[0].1);
     let order_plot = Plot::new(500, 500, 0..order[0].len() as i32, 0.0..1.0);
-    board.printfile("data.csv");
+    report("data.csv", board.printfile("data.csv"));
+    report("data.map", board.printmap("data.map"));
 
-    energy_plot.plot_timeseries("Energy.svg", "Total system energy", energy, &RED, "energy");
-    let labels: Vec<String> = vec!["1".to_owned(), "2".to_owned(), "3".to_owned(),];
-    order_plot.plot_multiple_timeseries("Order.svg", "Total system order", order, &BLUE, &RED, labels);
+    //every recorded observable, the per step series as binary since it can be millions of rows long
+    let mut columns = vec!["energy".to_string()];
+    columns.extend((1..=order.len()).map(|sublattice| format!("order {}", sublattice)));
+    let mut step_series = Timeseries::new(&columns.iter().map(|column| column.as_str()).collect::<Vec<&str>>());
+    step_series.set_board(&board);
+    for (i, &(step, value)) in energy.iter().enumerate() {
+        let mut values = vec![value];
+        values.extend(order.iter().map(|series| series[i].1));
+        step_series.push(step as i64, values);
+    }
+    let mut sweep_series = Timeseries::new(&["psi", "mean domain size", "domains"]);
+    sweep_series.set_board(&board);
+    for ((&(step, value), &(_, size)), &(_, count)) in psi.iter().zip(domain_size.iter()).zip(domain_count.iter()) {
+        sweep_series.push(step as i64, vec![value, size, count]);
+    }
+    for (series, stem, format) in [(step_series, "data steps", Format::Npy), (sweep_series, "data sweeps", Format::Csv)] {
+        report(stem, series.write(stem, format));
+    }
+
+    let energy_per_sweep: Vec<f64> = energy.iter().step_by(sweep as usize).map(|e| e.1).collect();
+    let psi_per_sweep: Vec<f64> = psi.iter().map(|p| p.1).collect();
+    for (name, series) in [("energy", energy_per_sweep), ("|psi|", psi_per_sweep)] {
+        let sampling = Sampling::new(&series);
+        println!("> {}: tau {:.1} sweeps, {:.0} effective samples out of {}", name, sampling.tau, sampling.effective_samples, series.len());
+    }
+
+    let domains = Domains::new(&board);
+    report("domains.csv", domains.printfile("domains.csv"));
+    println!("> Domains: {}, mean size: {:.2}, wall length: {}", domains.count(), domains.mean_size(), domains.wall_length);
+    println!("domains per sublattice: {:?}", domains.count_per_sublattice());
+    for (size, number) in domains.size_distribution() {
+        println!("domain size {}: {}", size, number);
+    }
+    let max_domain_size = domain_size.iter().fold(1.0, |max: f64, value| max.max(value.1));
+    let domain_plot = Plot::new(500, 500, 0..energy.len() as i32, 0.0..max_domain_size);
+    report("Domains.svg", domain_plot.plot_timeseries("Domains.svg", "Mean domain size", domain_size, &BLUE, "mean size"));
+
+    report("Energy.svg", energy_plot.plot_timeseries("Energy.svg", "Total system energy", energy, &RED, "energy"));
+    let labels: Vec<String> = (1..=order.len()).map(|sublattice| sublattice.to_string()).collect();
+    report("Order.svg", order_plot.plot_multiple_timeseries("Order.svg", "Total system order", order, &BLUE, &RED, labels));
+
+    //a binary mixture at the same parameters, where unlike particles repel half as much and a tenth of the steps swap species
+    let mut mixture = HexBoard::new(30, Generator::new(Backend::ChaCha12, 123067891, 0), 5.0, 1.0/3.0);
+    mixture.set_species(vec![vec![1.0, 0.5], vec![0.5, 1.0]], vec![0.5, 0.5], 0.1);
+    mixture.initialize();
+    let mut species_a = observer::species_order(0, 1);
+    let mut species_b = observer::species_order(1, 1);
+    //unlike bonds cost half a bond and total_energy counts every bond twice, so the energy moves in steps of 1, two to a bin
+    let mut mixture_histograms = observer::Histograms::new(2.0, 1);
+    let mixture_lenght = 2000 * sweep;
+    for x in 0..mixture_lenght {
+        mixture.advance_timestep_repulsive();
+        observer::observe(&mut [&mut species_a, &mut species_b, &mut mixture_histograms], &mixture, x);
+    }
+    println!("> Mixture: {} of {} swaps accepted, energy {:.1}", mixture.accepted_swaps, mixture.attempted_swaps, mixture.total_energy() / 2.0);
+    report("mixture.csv", mixture.printfile("mixture.csv"));
+    report("mixture.map", mixture.printmap("mixture.map"));
+    report("mixture histograms.csv", mixture_histograms.write_csv("mixture histograms.csv", &format!("{} at step {}", mixture.rng, mixture.steps)));
+    report("mixture histograms", mixture_histograms.plot("Mixture", "Mixture"));
+    let sublattices = species_a.series.len();
+    let mixture_labels: Vec<String> = ["A", "B"].iter().flat_map(|species| (1..=sublattices).map(move |sublattice| format!("{} {}", species, sublattice))).collect();
+    let mixture_orders: Vec<Vec<(i32, f64)>> = species_a.series.into_iter().chain(species_b.series).collect();
+    let mixture_plot = Plot::new(500, 500, 0..mixture_lenght, 0.0..1.0);
+    report("Mixture order.svg", mixture_plot.plot_multiple_timeseries("Mixture order.svg", "Sublattice order per species", mixture_orders, &BLUE, &RED, mixture_labels));
+
+    //the sampler against the exact canonical averages of a board small enough to enumerate every configuration
+    let mut tiny = HexBoard::new(6, Generator::new(Backend::ChaCha12, 123067892, 0), 1.0, 4.0/36.0);
+    tiny.initialize();
+    let exact = exact::Enumeration::new(&tiny);
+    let mut tiny_energy = vec![];
+    let mut tiny_psi = vec![];
+    for _ in 0..20000 {
+        for _ in 0..36 {
+            tiny.advance_timestep_repulsive();
+        }
+        tiny_energy.push(exact::energy(&tiny));
+        tiny_psi.push(tiny.get_order_parameter());
+    }
+    let (sampled_energy, sampled_psi) = (ErrorMethod::Blocking.estimate(&tiny_energy), ErrorMethod::Blocking.estimate(&tiny_psi));
+    println!("> Exact enumeration of {} configurations: ln Z {:.4}, <E> {:.4} (var {:.4}), <|psi|> {:.4}", exact.configurations, exact.log_partition_function, exact.mean_energy, exact.energy_variance, exact.mean_psi());
+    println!("> Sampled: <E> {:.4} +- {:.4}, <|psi|> {:.4} +- {:.4}", sampled_energy.mean, sampled_energy.error, sampled_psi.mean, sampled_psi.error);
 
     //run batch runs to evaluate order varying different parameters of the simulation
     let simlenght = 1000000;
-    let fill_batchrun = batchrun::Batchrun::new(5, "Fill variation");
-    let betaj_batchrun = batchrun::Batchrun::new(5, "Betaj variation");
-    let betaj_batchrun_small = batchrun::Batchrun::new(5, "Betaj variation around transition temperature");
-    let even_size_batchrun = batchrun::Batchrun::new(5, "Grid size variation");
-    
-    fill_batchrun.fill_test(30, 10.0, simlenght, 0.1, 0.6);
-    betaj_batchrun.betaj_test(30, simlenght, 1.0, 6.0);
-    betaj_batchrun_small.betaj_test(30, simlenght * 3, 2.6, 3.1);
-    even_size_batchrun.size_test(6, 46, simlenght, 10.0);
+    let mut fill_batchrun = batchrun::Batchrun::new("Fill variation");
+    let mut betaj_batchrun = batchrun::Batchrun::new("Betaj variation");
+    let mut betaj_batchrun_small = batchrun::Batchrun::new("Betaj variation around transition temperature");
+    let mut even_size_batchrun = batchrun::Batchrun::new("Grid size variation");
+    let mut field_batchrun = batchrun::Batchrun::new("Sublattice field variation");
+    let mut coupling_batchrun = batchrun::Batchrun::new("Coupling and boundary variation");
+    let mut diffusion_batchrun = batchrun::Batchrun::new("Mean squared displacement");
+    let mut phase_batchrun = batchrun::Batchrun::new("Phase diagram");
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    for batchrun in [&mut fill_batchrun, &mut betaj_batchrun, &mut betaj_batchrun_small, &mut even_size_batchrun, &mut field_batchrun, &mut coupling_batchrun, &mut diffusion_batchrun, &mut phase_batchrun] {
+        batchrun.set_threads(threads);
+    }
+    report("burn-in", fill_batchrun.set_burnin(vec![Criterion::Plateau { window: 50, tolerance: 0.01 }, Criterion::MaxSteps(simlenght)]));
+    report("burn-in", betaj_batchrun.set_burnin(vec![Criterion::Geweke { z: 2.0 }, Criterion::MaxSteps(simlenght)]));
+    report("burn-in", betaj_batchrun_small.set_burnin(vec![Criterion::Replicas { window: 50, tolerance: 0.02 }, Criterion::MaxSteps(simlenght * 3)]));
+    fill_batchrun.set_replicas(4, ErrorMethod::Blocking);
+    betaj_batchrun.set_replicas(4, ErrorMethod::Jackknife { blocks: 20 });
+    betaj_batchrun_small.set_replicas(4, ErrorMethod::Bootstrap { resamples: 200, seed: 98765 });
+    //two peaks in the energy or |psi| histograms of a point show the coexistence of the ordered and disordered phases
+    report("histograms", betaj_batchrun_small.set_histograms(4.0));
+    report("checkpoint directory", betaj_batchrun_small.set_checkpoints("checkpoints", simlenght / 10));
+    even_size_batchrun.set_replicas(4, ErrorMethod::Blocking);
+    report("output directory", fill_batchrun.set_output("series", Format::Csv));
+    report("output directory", betaj_batchrun.set_output("series", Format::JsonLines));
+    report("output directory", phase_batchrun.set_output("series", Format::Npy));
+    //the final configuration of every diffusion run, to see how far the tracked particles spread
+    report("snapshot directory", diffusion_batchrun.set_snapshots("snapshots", simlenght));
+    //the many short points of the phase diagram draw from pcg64, its seed and the stream of every replica are in the sidecars
+    phase_batchrun.set_generator(Backend::Pcg64, 20240601);
+
+    let fill_sweep = Sweep::new(Parameters::new(30, 1.0/3.0, 10.0), Parameter::Fill, Values::Linear { min: 0.1, max: 0.6, steps: 5 });
+    let betaj_sweep = Sweep::new(Parameters::new(30, 1.0/3.0, 1.0), Parameter::Betaj, Values::Linear { min: 1.0, max: 6.0, steps: 5 });
+    let betaj_sweep_small = Sweep::new(Parameters::new(30, 1.0/3.0, 1.0), Parameter::Betaj, Values::Linear { min: 2.6, max: 3.1, steps: 5 });
+    let size_sweep = Sweep::new(Parameters::new(30, 1.0/3.0, 10.0).stored_as(Storage::Packed), Parameter::Size, Values::Linear { min: 6.0, max: 46.0, steps: 5 });
+    let field_sweep = Sweep::new(Parameters::new(30, 1.0/3.0, 2.0), Parameter::Field, Values::Log { min: 0.1, max: 10.0, steps: 5 });
+    let coupling_sweep = Sweep::grid(
+        Parameters::new(30, 1.0/3.0, 3.0),
+        (Parameter::Coupling, Values::List(vec![-1.0, 1.0])),
+        (Parameter::Boundary, Values::List(vec![0.0, 1.0])),
+    );
+    let diffusion_sweep = Sweep::grid(
+        Parameters::new(30, 1.0/3.0, 1.0),
+        (Parameter::Betaj, Values::List(vec![1.0, 5.0])),
+        (Parameter::Fill, Values::Linear { min: 0.1, max: 0.6, steps: 5 }),
+    );
+
+    //a failed batch is reported and the next one still runs
+    report("Fill variation", fill_batchrun.run_sweep(&fill_sweep, simlenght));
+    report("Betaj variation", betaj_batchrun.run_sweep(&betaj_sweep, simlenght));
+    report("Betaj variation around transition temperature", betaj_batchrun_small.run_sweep(&betaj_sweep_small, simlenght * 3));
+    report("Grid size variation", even_size_batchrun.run_sweep(&size_sweep, simlenght));
+    report("Sublattice field variation", field_batchrun.run_sweep(&field_sweep, simlenght));
+    for record in report("Coupling and boundary variation", coupling_batchrun.run_sweep(&coupling_sweep, simlenght)).unwrap_or_default() {
+        println!("{}", record);
+    }
+    report("Mean squared displacement", diffusion_batchrun.diffusion_test(&diffusion_sweep, simlenght));
+    report("burn-in", phase_batchrun.set_burnin(vec![Criterion::Geweke { z: 2.0 }, Criterion::MaxSteps(simlenght)]));
+    report("Phase diagram", phase_batchrun.phase_diagram(
+        Parameters::new(30, 1.0/3.0, 1.0),
+        Values::Linear { min: 0.1, max: 0.8, steps: 14 },
+        Values::Linear { min: 0.5, max: 6.0, steps: 11 },
+        simlenght,
+    ));
+
+    //the points around the transition again, from a cold start and from structured starts, to compare with the hot start above
+    let starts = [InitialState::Ordered(0), InitialState::Domains, InitialState::Slab, InitialState::Nucleus(5.0)];
+    for initial in starts {
+        let base = Parameters::new(30, 1.0/3.0, 1.0).starting_from(initial);
+        let title = format!("Betaj variation from {}", initial);
+        let mut start_batchrun = batchrun::Batchrun::new(&title);
+        start_batchrun.set_threads(threads);
+        report(&title, start_batchrun.run_sweep(&Sweep::new(base, Parameter::Betaj, Values::Linear { min: 2.6, max: 3.1, steps: 5 }), simlenght));
+    }
+
+    //a large board updated with parallel sweeps, the blocks of one colour of the decomposition running on every thread at once.
+    //its cells are bit-packed, the occupancy of 64 cells to a word
+    let mut large = HexBoard::with_storage(600, Generator::new(Backend::ChaCha12, 123067893, 0), 5.0, 1.0/3.0, Storage::Packed);
+    large.initialize();
+    let decomposition = parallel::Decomposition::new(20, threads);
+    let start = Instant::now();
+    for _ in 0..200 {
+        decomposition.sweep(&mut large);
+    }
+    println!("> Parallel sweeps: {} move attempts on a 600x600 board in {:.1} s, acceptance {:.3}, |psi| {:.3}", large.steps, start.elapsed().as_secs_f64(), large.acceptance_rate(), large.get_order_parameter());
+    println!("> Cells of the 600x600 board: {} kB {}, {} kB {}", large.grid.bytes() / 1000, large.storage(), large.grid.converted(Storage::Cells).bytes() / 1000, Storage::Cells);
+
+    //the ordered phase on a disordered substrate, averaged over realisations: pinned impurities on a clean and on a partly blocked
+    //substrate, and random on-site energies
+    let mut impurity_batchrun = batchrun::Batchrun::new("Impurity and blocked site variation");
+    let mut site_energy_batchrun = batchrun::Batchrun::new("Site energy variation");
+    for batchrun in [&mut impurity_batchrun, &mut site_energy_batchrun] {
+        batchrun.set_threads(threads);
+        batchrun.set_replicas(2, ErrorMethod::Blocking);
+        batchrun.set_realisations(4, 24681357);
+    }
+    let impurity_sweep = Sweep::grid(
+        Parameters::new(30, 1.0/3.0, 5.0),
+        (Parameter::Blocked, Values::List(vec![0.0, 0.05])),
+        (Parameter::Impurities, Values::Linear { min: 0.0, max: 0.1, steps: 5 }),
+    );
+    let gaussian = Substrate{site_energies: SiteEnergies::Gaussian(0.0), ..Substrate::clean()};
+    let site_energy_sweep = Sweep::new(Parameters::new(30, 1.0/3.0, 5.0).on_substrate(gaussian), Parameter::SiteEnergies, Values::Linear { min: 0.0, max: 2.0, steps: 5 });
+    for record in report("Impurity and blocked site variation", impurity_batchrun.run_sweep(&impurity_sweep, simlenght)).unwrap_or_default() {
+        println!("{}", record);
+    }
+    report("Site energy variation", site_energy_batchrun.run_sweep(&site_energy_sweep, simlenght));
+
+    //the same dynamics and measurements on the other lattices, each at the filling where one of its sublattices can be filled
+    let lattices: [(&'static dyn lattice::Lattice, f64); 3] = [(&lattice::HONEYCOMB, 0.5), (&lattice::SQUARE, 0.5), (&lattice::KAGOME, 1.0/3.0)];
+    for (lattice, fill) in lattices {
+        let title = format!("Betaj variation on the {} lattice", lattice.name());
+        let mut lattice_batchrun = batchrun::Batchrun::new(&title);
+        lattice_batchrun.set_threads(threads);
+        let base = Parameters::new(30, fill, 1.0).on_lattice(lattice);
+        report(&title, lattice_batchrun.run_sweep(&Sweep::new(base, Parameter::Betaj, Values::Linear { min: 1.0, max: 6.0, steps: 5 }), simlenght));
+    }
 
 }
@@ -0,0 +1,261 @@
+use crate::HexBoard;
+use crate::domains::Domains;
+use crate::statistics::Histogram;
+use crate::plot::Plot;
+use crate::error::{report, Result};
+
+use plotters::prelude::*;
+use std::fs::File;
+use std::io::{prelude::*, BufWriter};
+
+//width of the bins of the |psi| and sublattice fraction histograms, 50 bins over [0, 1]
+const FRACTION_BIN: f64 = 0.02;
+
+//a measurement attached to a run. every hook gets read access to the board, on_step is called every
+//interval steps and on_sweep every interval sweeps (size^2 steps), counted from the first step of the run
+pub trait Observer {
+    fn interval(&self) -> i32 {
+        return 1;
+    }
+
+    fn on_step(&mut self, _board: &HexBoard, _step: i32) {}
+
+    fn on_sweep(&mut self, _board: &HexBoard, _step: i32) {}
+
+    fn on_finish(&mut self, _board: &HexBoard) {}
+}
+
+//calls the hooks of the observers that are due after step, where step counts the moves of the run from 0
+pub fn observe(observers: &mut [&mut dyn Observer], board: &HexBoard, step: i32) {
+    let sweep = board.size as i32 * board.size as i32;
+    for observer in observers.iter_mut() {
+        let interval = observer.interval().max(1);
+        if step % interval == 0 {
+            observer.on_step(board, step);
+        }
+        if step % sweep == 0 && (step / sweep) % interval == 0 {
+            observer.on_sweep(board, step);
+        }
+    }
+}
+
+//calls the on_finish hook of every observer once the run is over
+pub fn finish(observers: &mut [&mut dyn Observer], board: &HexBoard) {
+    for observer in observers.iter_mut() {
+        observer.on_finish(board);
+    }
+}
+
+//records one or more values measured on the board, as timeseries of (step, value)
+pub struct Measurement<F: FnMut(&HexBoard) -> Vec<f64>> {
+    measure: F,
+    per_sweep: bool,
+    interval: i32,
+    pub series: Vec<Vec<(i32, f64)>>,
+}
+
+//the measurements provided by the crate, plain functions of the board
+pub type Builtin = Measurement<fn(&HexBoard) -> Vec<f64>>;
+
+impl<F: FnMut(&HexBoard) -> Vec<f64>> Measurement<F> {
+    //measures every interval steps
+    pub fn per_step(interval: i32, measure: F) -> Self {
+        return Measurement{measure, per_sweep: false, interval, series: vec![]};
+    }
+
+    //measures every interval sweeps
+    pub fn per_sweep(interval: i32, measure: F) -> Self {
+        return Measurement{measure, per_sweep: true, interval, series: vec![]};
+    }
+
+    fn record(&mut self, board: &HexBoard, step: i32) {
+        let values = (self.measure)(board);
+        if self.series.len() < values.len() {
+            self.series.resize(values.len(), vec![]);
+        }
+        for (series, value) in self.series.iter_mut().zip(values) {
+            series.push((step, value));
+        }
+    }
+
+    //the timeseries of the first measured value
+    pub fn first(&self) -> &[(i32, f64)] {
+        return self.series.first().map_or(&[], |series| series.as_slice());
+    }
+}
+
+impl<F: FnMut(&HexBoard) -> Vec<f64>> Observer for Measurement<F> {
+    fn interval(&self) -> i32 {
+        return self.interval;
+    }
+
+    fn on_step(&mut self, board: &HexBoard, step: i32) {
+        if !self.per_sweep {
+            self.record(board, step);
+        }
+    }
+
+    fn on_sweep(&mut self, board: &HexBoard, step: i32) {
+        if self.per_sweep {
+            self.record(board, step);
+        }
+    }
+}
+
+//total energy, every interval steps
+pub fn energy(interval: i32) -> Builtin {
+    return Measurement::per_step(interval, |board| vec![board.total_energy()]);
+}
+
+//fractions of the particles on each sublattice of the board, every interval steps
+pub fn order(interval: i32) -> Builtin {
+    return Measurement::per_step(interval, |board| board.get_order().into_iter().map(|o| o as f64).collect());
+}
+
+//fractions of the particles of one species on each sublattice of the board, every interval sweeps
+pub fn species_order(species: u8, interval: i32) -> Measurement<impl FnMut(&HexBoard) -> Vec<f64>> {
+    return Measurement::per_sweep(interval, move |board: &HexBoard| board.get_species_order(species).into_iter().map(|o| o as f64).collect());
+}
+
+//|psi|, every interval sweeps
+pub fn psi(interval: i32) -> Builtin {
+    return Measurement::per_sweep(interval, |board| vec![board.get_order_parameter()]);
+}
+
+//mean domain size and number of domains, from a single labelling of the board every interval sweeps
+pub fn domains(interval: i32) -> Builtin {
+    return Measurement::per_sweep(interval, |board| {
+        let domains = Domains::new(board);
+        return vec![domains.mean_size(), domains.count() as f64];
+    });
+}
+
+//mean squared displacement of the tracked particles, every interval sweeps
+pub fn msd(interval: i32) -> Builtin {
+    return Measurement::per_sweep(interval, |board| vec![board.tracker.as_ref().map_or(0.0, |tracker| tracker.msd())]);
+}
+
+//histograms of the total energy, |psi| and the fraction of every sublattice of get_order, every interval sweeps.
+//a double peaked distribution near a transition shows the coexistence of the ordered and disordered phases
+#[derive(Clone)]
+pub struct Histograms {
+    interval: i32,
+    pub energy: Histogram,
+    pub psi: Histogram,
+    pub order: Vec<Histogram>,
+}
+
+impl Histograms {
+    //energy_width is the width of the energy bins, a multiple of twice the smallest interaction keeps every bin the same
+    //number of bond energies wide
+    pub fn new(energy_width: f64, interval: i32) -> Self {
+        return Histograms{interval, energy: Histogram::new(energy_width), psi: Histogram::new(FRACTION_BIN), order: vec![]};
+    }
+
+    //adds a sample of the observables, orders may be empty when the sublattice fractions were not recorded
+    pub fn record(&mut self, energy: f64, psi: f64, orders: &[f64]) {
+        self.energy.add(energy);
+        self.psi.add(psi);
+        if self.order.len() < orders.len() {
+            self.order.resize(orders.len(), Histogram::new(FRACTION_BIN));
+        }
+        for (histogram, &order) in self.order.iter_mut().zip(orders) {
+            histogram.add(order);
+        }
+    }
+
+    //adds the counts of other, for example another replica of the same point
+    pub fn merge(&mut self, other: &Histograms) {
+        self.energy.merge(&other.energy);
+        self.psi.merge(&other.psi);
+        if self.order.len() < other.order.len() {
+            self.order.resize(other.order.len(), Histogram::new(FRACTION_BIN));
+        }
+        for (histogram, other) in self.order.iter_mut().zip(&other.order) {
+            histogram.merge(other);
+        }
+    }
+
+    //every histogram by name, the sublattice fractions as order 1, order 2, ...
+    fn named(&self) -> Vec<(String, &Histogram)> {
+        let mut named = vec![("energy".to_string(), &self.energy), ("psi".to_string(), &self.psi)];
+        named.extend(self.order.iter().enumerate().map(|(i, histogram)| (format!("order {}", i + 1), histogram)));
+        return named;
+    }
+
+    //writes every bin of every histogram to path as csv rows of observable, lower edge, upper edge, count and probability,
+    //after a comment line naming the random generator the samples were drawn from
+    pub fn write_csv(&self, path: &str, generator: &str) -> Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "# {}", generator)?;
+        writeln!(file, "observable,lower,upper,count,probability")?;
+        for (name, histogram) in self.named() {
+            let total = histogram.total().max(1) as f64;
+            for (lower, upper, count) in histogram.bins() {
+                writeln!(file, "{},{},{},{},{}", name, lower, upper, count, count as f64 / total)?;
+            }
+        }
+        file.flush()?;
+        return Ok(());
+    }
+
+    //draws the energy and |psi| histograms as bar charts of probabilities to "{stem} energy histogram.svg" and "{stem} psi histogram.svg"
+    pub fn plot(&self, stem: &str, caption: &str) -> Result<()> {
+        for (name, histogram, color) in [("energy", &self.energy, &RED), ("psi", &self.psi, &BLUE)] {
+            let bins = histogram.probabilities();
+            let (Some(first), Some(last)) = (bins.first(), bins.last()) else {
+                continue;
+            };
+            let margin = (last.1 - first.0) * 0.05;
+            let highest = bins.iter().fold(0.0, |max: f64, bin| max.max(bin.2));
+            let plot = Plot::new(500, 500, 0..1, 0.0..highest * 1.1);
+            let label = if name == "psi" { "|psi|" } else { name };
+            plot.plot_histogram(&format!("{} {} histogram.svg", stem, name), &format!("{} {}", caption, label), (first.0 - margin)..(last.1 + margin), bins, color, label)?;
+        }
+        return Ok(());
+    }
+}
+
+impl Observer for Histograms {
+    fn interval(&self) -> i32 {
+        return self.interval;
+    }
+
+    fn on_sweep(&mut self, board: &HexBoard, _step: i32) {
+        let orders: Vec<f64> = board.get_order().into_iter().map(|o| o as f64).collect();
+        self.record(board.total_energy(), board.get_order_parameter(), &orders);
+    }
+}
+
+//writes an ascii map of the board to directory every interval sweeps and at the end of the run.
+//a failed write is reported and the run goes on
+pub struct Snapshots {
+    directory: String,
+    interval: i32,
+}
+
+impl Snapshots {
+    pub fn new(directory: &str, interval: i32) -> Result<Self> {
+        std::fs::create_dir_all(directory)?;
+        return Ok(Snapshots{directory: directory.to_string(), interval});
+    }
+
+    fn write(&self, board: &HexBoard, name: &str) {
+        let path = format!("{}/{}.map", self.directory, name);
+        report(&format!("could not write snapshot {}", path), board.printmap(&path));
+    }
+}
+
+impl Observer for Snapshots {
+    fn interval(&self) -> i32 {
+        return self.interval;
+    }
+
+    fn on_sweep(&mut self, board: &HexBoard, step: i32) {
+        self.write(board, &format!("step {}", step));
+    }
+
+    fn on_finish(&mut self, board: &HexBoard) {
+        self.write(board, "final");
+    }
+}
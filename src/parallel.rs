@@ -0,0 +1,301 @@
+use crate::hexboard::{Boundary, Hex, HexBoard};
+use crate::random::Generator;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+//splits the board into blocks x blocks rectangles coloured like a 2 x 2 checkerboard and updates the blocks of one colour
+//at the same time. a move only changes cells of its own block and reads their neighbours, which belong to blocks of
+//other colours that stay frozen during the phase, so the blocks of a colour never see each other. every block runs
+//the metropolis moves of advance_timestep_repulsive restricted to its own cells, which keeps detailed balance
+//for each phase, on a copy of its cells; the copies are written back between phases. every phase draws a seed from the
+//board, and block i of the phase draws from stream i of that seed on the backend of the board
+pub struct Decomposition {
+    blocks: u16,
+    threads: usize,
+}
+
+//origin and extent of a block of a phase
+type Placement = ((u16, u16), (u16, u16));
+
+//a jump between two cells of a block with its displacement in the plane
+type Jump = ((u16, u16), (u16, u16), (f64, f64));
+
+//what a block did during a phase: its cells, the jumps for the tracker and the counters of the board
+struct BlockResult {
+    cells: Vec<Hex>,
+    jumps: Vec<Jump>,
+    attempted_jumps: u64,
+    accepted_jumps: u64,
+    attempted_swaps: u64,
+    accepted_swaps: u64,
+}
+
+//a block of the current phase: its origin on the board, its extent and a copy of its cells, the rest of the board is read
+//from the frozen board
+struct Block<'a> {
+    board: &'a HexBoard,
+    origin: (u16, u16),
+    extent: (u16, u16),
+    cells: Vec<Hex>,
+    rng: Generator,
+}
+
+impl Decomposition {
+    //blocks along each side must be even, so the colours still alternate across the periodic edges.
+    //threads only sets how many blocks run at once, the results only depend on the seed of the board
+    pub fn new(blocks: u16, threads: usize) -> Self {
+        assert!(blocks >= 2 && blocks % 2 == 0, "the number of blocks along a side must be even");
+        return Decomposition{blocks, threads: threads.max(1)};
+    }
+
+    //one sweep of board, size x size move attempts in four phases, one per colour. the block grid is shifted by a random
+    //offset and the colours run in a random order every sweep, so particles cross the block edges and the sweep as a whole
+    //keeps detailed balance. steps and the counters of the board advance as for the serial moves
+    pub fn sweep(&self, board: &mut HexBoard) {
+        assert!(board.size / self.blocks >= 2, "blocks must be at least 2 cells wide");
+        let offset = (board.rng.gen_range(0..board.size), board.rng.gen_range(0..board.size));
+        let mut colours = [(0, 0), (0, 1), (1, 0), (1, 1)];
+        colours.shuffle(&mut board.rng);
+        for colour in colours {
+            let blocks: Vec<Placement> = self.coloured(board.size, offset, colour);
+            let seed = board.rng.gen();
+            let results = self.run_blocks(board, &blocks, seed);
+            for ((_, extent), result) in blocks.iter().zip(results) {
+                for hex in result.cells {
+                    board.grid.set(hex);
+                }
+                if let Some(tracker) = board.tracker.as_mut() {
+                    for (from, to, displacement) in result.jumps {
+                        tracker.hop(from, to, displacement);
+                    }
+                }
+                board.steps += extent.0 as u64 * extent.1 as u64;
+                board.attempted_jumps += result.attempted_jumps;
+                board.accepted_jumps += result.accepted_jumps;
+                board.attempted_swaps += result.attempted_swaps;
+                board.accepted_swaps += result.accepted_swaps;
+            }
+        }
+    }
+
+    //origins and extents of the blocks of colour, with the block grid shifted by offset
+    fn coloured(&self, size: u16, offset: (u16, u16), colour: (u16, u16)) -> Vec<Placement> {
+        let edge = |k: u16| (k as u32 * size as u32 / self.blocks as u32) as u16;
+        let mut blocks = vec![];
+        for bx in (colour.0..self.blocks).step_by(2) {
+            for by in (colour.1..self.blocks).step_by(2) {
+                let origin = ((offset.0 + edge(bx)) % size, (offset.1 + edge(by)) % size);
+                blocks.push((origin, (edge(bx + 1) - edge(bx), edge(by + 1) - edge(by))));
+            }
+        }
+        return blocks;
+    }
+
+    //runs the blocks on the threads, each one on a copy of its cells, and returns their results in order
+    fn run_blocks(&self, board: &HexBoard, blocks: &[Placement], seed: u64) -> Vec<BlockResult> {
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<BlockResult>>> = Mutex::new((0..blocks.len()).map(|_| None).collect());
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(blocks.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= blocks.len() {
+                        break;
+                    }
+                    let (origin, extent) = blocks[index];
+                    let rng = Generator::new(board.rng.backend(), seed, index as u64);
+                    let result = Block::new(board, origin, extent, rng).run();
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+        return results.into_inner().unwrap().into_iter().map(|result| result.unwrap()).collect();
+    }
+}
+
+impl<'a> Block<'a> {
+    fn new(board: &'a HexBoard, origin: (u16, u16), extent: (u16, u16), rng: Generator) -> Self {
+        let cells = (0..extent.0 as usize * extent.1 as usize).map(|i| {
+            let (x, y) = Block::global(board.size, origin, extent, i);
+            return board.get_cell(x as i16, y as i16);
+        }).collect();
+        return Block{board, origin, extent, cells, rng};
+    }
+
+    //board coordinates of the i-th cell of a block, the blocks wrap around the edges of the board
+    fn global(size: u16, origin: (u16, u16), extent: (u16, u16), i: usize) -> (u16, u16) {
+        let x = (origin.0 as usize + i / extent.1 as usize) % size as usize;
+        let y = (origin.1 as usize + i % extent.1 as usize) % size as usize;
+        return (x as u16, y as u16);
+    }
+
+    //index of the board cell x, y in the block, None outside it
+    fn local(&self, x: u16, y: u16) -> Option<usize> {
+        let size = self.board.size;
+        let lx = (x + size - self.origin.0) % size;
+        let ly = (y + size - self.origin.1) % size;
+        if lx < self.extent.0 && ly < self.extent.1 {
+            return Some(lx as usize * self.extent.1 as usize + ly as usize);
+        }
+        return None;
+    }
+
+    //the cell x, y as the block sees it, its own copy inside and the frozen board outside
+    fn cell(&self, x: u16, y: u16) -> Hex {
+        return match self.local(x, y) {
+            Some(i) => self.cells[i],
+            None => self.board.get_cell(x as i16, y as i16),
+        };
+    }
+
+    //the neighbour of hex along offset, None when it falls off an open board
+    fn neighbour(&self, hex: Hex, offset: (i16, i16)) -> Option<Hex> {
+        let board = self.board;
+        if board.boundary == Boundary::Open && !board.is_inside(hex.x + offset.0, hex.y + offset.1) {
+            return None;
+        }
+        return Some(self.cell(board.wrapx(hex.x + offset.0), board.wrapy(hex.y + offset.1)));
+    }
+
+    //get_species_energy of the board on the cells seen by the block
+    fn species_energy(&self, hex: Hex, species: u8, skip: Option<Hex>) -> f64 {
+        let row = &self.board.interactions[species as usize];
+        let mut energy = 0.0;
+        for &offset in self.board.get_directions(hex.x, hex.y) {
+            if let Some(neighbour) = self.neighbour(hex, offset) {
+                let skipped = skip.is_some_and(|skip| skip.x == neighbour.x && skip.y == neighbour.y);
+                if neighbour.value && !skipped {
+                    energy += row[neighbour.species as usize];
+                }
+            }
+        }
+        return energy;
+    }
+
+    fn accept(&mut self, delta: f64) -> bool {
+        return delta < 0.0 || self.rng.gen_bool((-delta * self.board.disorder).exp());
+    }
+
+    //one attempt per cell of the block, jumps and swaps that would leave the block are rejected
+    fn run(mut self) -> BlockResult {
+        let mut result = BlockResult{cells: vec![], jumps: vec![], attempted_jumps: 0, accepted_jumps: 0, attempted_swaps: 0, accepted_swaps: 0};
+        let board = self.board;
+        for _ in 0..self.cells.len() {
+            let swap = board.swap_probability > 0.0 && self.rng.gen_bool(board.swap_probability);
+            let i = self.rng.gen_range(0..self.cells.len());
+            let hex = self.cells[i];
+            let directions = board.get_directions(hex.x, hex.y);
+            let offset = directions[self.rng.gen_range(0..directions.len())];
+            if !board.is_particle(hex) {
+                continue;
+            }
+            if !swap {
+                result.attempted_jumps += 1;
+            }
+            let Some(other) = self.neighbour(hex, offset) else { continue };
+            let Some(j) = self.local(other.x as u16, other.y as u16) else { continue };
+            if swap {
+                if !board.is_particle(other) || other.species == hex.species {
+                    continue;
+                }
+                result.attempted_swaps += 1;
+                let before = self.species_energy(hex, hex.species, Some(other)) + self.species_energy(other, other.species, Some(hex));
+                let after = self.species_energy(hex, other.species, Some(other)) + self.species_energy(other, hex.species, Some(hex));
+                if self.accept(board.coupling * (after - before)) {
+                    self.cells[i].species = other.species;
+                    self.cells[j].species = hex.species;
+                    result.accepted_swaps += 1;
+                }
+                continue;
+            }
+            if other.value || board.is_blocked(other.x, other.y) {
+                continue;
+            }
+            let start_energy = self.species_energy(hex, hex.species, None);
+            let end_energy = self.species_energy(other, hex.species, None) - board.interactions[hex.species as usize][hex.species as usize];
+            let delta = board.coupling * (end_energy - start_energy)
+                + board.field * (board.get_field_weight(hex) - board.get_field_weight(other))
+                + board.get_site_energy(other) - board.get_site_energy(hex);
+            if self.accept(delta) {
+                self.cells[i].value = false;
+                self.cells[j].value = true;
+                self.cells[j].species = hex.species;
+                let start = board.get_position(hex.x, hex.y);
+                let end = board.get_position(hex.x + offset.0, hex.y + offset.1);
+                result.jumps.push(((hex.x as u16, hex.y as u16), (other.x as u16, other.y as u16), (end.0 - start.0, end.1 - start.1)));
+                result.accepted_jumps += 1;
+            }
+        }
+        result.cells = self.cells;
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exact::{self, Enumeration};
+    use crate::lattice;
+    use crate::statistics::ErrorMethod;
+    use crate::substrate::{SiteEnergies, Substrate};
+    use crate::random::Backend;
+
+    #[test]
+    fn parallel_sweeps_sample_the_exact_distribution() {
+        let mut square = HexBoard::new(4, Generator::new(Backend::ChaCha12, 5, 0), 1.5, 0.5);
+        square.set_lattice(&lattice::SQUARE).unwrap();
+        square.set_boundary(Boundary::Open);
+        square.set_field(0.5);
+        square.set_substrate(Substrate{blocked: 0.0625, impurities: 0.0625, site_energies: SiteEnergies::Gaussian(0.5)}, 3);
+        //blocks 2 cells wide on the triangular board, the narrowest ones allowed
+        let triangular = HexBoard::new(8, Generator::new(Backend::ChaCha12, 4, 0), 1.0, 3.0 / 64.0);
+        for (mut board, blocks) in [(triangular, 4), (square, 2)] {
+            board.initialize();
+            let exact = Enumeration::new(&board);
+            let decomposition = Decomposition::new(blocks, 2);
+            let mut energies = vec![];
+            let mut psis = vec![];
+            for i in 0..31000 {
+                decomposition.sweep(&mut board);
+                if i >= 1000 {
+                    energies.push(exact::energy(&board));
+                    psis.push(board.get_order_parameter());
+                }
+            }
+            for (name, estimate, expected) in [("<E>", ErrorMethod::Blocking.estimate(&energies), exact.mean_energy), ("<|psi|>", ErrorMethod::Blocking.estimate(&psis), exact.mean_psi())] {
+                assert!((estimate.mean - expected).abs() < 4.0 * estimate.error + 1e-9,
+                    "{}: sampled {:.5} +- {:.5}, exact {:.5}", name, estimate.mean, estimate.error, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn sweeps_do_not_depend_on_the_threads() {
+        let boards: Vec<HexBoard> = [1, 3].into_iter().map(|threads| {
+            let mut board = HexBoard::new(24, Generator::new(Backend::ChaCha12, 77, 0), 2.0, 1.0 / 3.0);
+            board.set_species(vec![vec![1.0, 0.5], vec![0.5, 1.0]], vec![0.5, 0.5], 0.2);
+            board.set_substrate(Substrate{blocked: 0.05, impurities: 0.05, site_energies: SiteEnergies::Uniform(0.5)}, 9);
+            board.initialize();
+            board.enable_tracking();
+            let particles = board.tracker.as_ref().unwrap().positions.len();
+            let decomposition = Decomposition::new(4, threads);
+            for _ in 0..50 {
+                decomposition.sweep(&mut board);
+            }
+            let count = board.grid.hexes().filter(|&hex| board.is_particle(hex)).count();
+            assert_eq!(count, particles);
+            assert_eq!(board.steps, 50 * 24 * 24);
+            return board;
+        }).collect();
+        let cells = |board: &HexBoard| -> Vec<(bool, u8)> { board.grid.hexes().map(|hex| (hex.value, hex.species)).collect() };
+        assert!(cells(&boards[0]) == cells(&boards[1]));
+        assert_eq!(boards[0].accepted_jumps, boards[1].accepted_jumps);
+        assert_eq!(boards[0].accepted_swaps, boards[1].accepted_swaps);
+        assert!(boards[0].tracker.as_ref().unwrap().positions == boards[1].tracker.as_ref().unwrap().positions);
+        assert!(boards[0].accepted_jumps > 0 && boards[0].accepted_swaps > 0);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::sweep::PointRecord;
+use crate::superstructure::Superstructure;
+
+use plotters::style::RGBColor;
+use std::fmt;
+
+//below this difference between the most and the least occupied sublattice a point is considered disordered.
+//|psi| is not used because its largest value depends on the number of sublattices
+const ORDER_THRESHOLD: f64 = 0.4;
+
+//phases of the lattice gas that can be told apart from the sublattice occupations
+#[derive(Clone, Copy, PartialEq)]
+pub enum Phase {
+    Disordered,
+    //one sublattice of the measured superstructure is filled, as in the sqrt(3) x sqrt(3) one at fill 1/3,
+    //or emptied for the complementary one. None on the sublattices of lattices other than the triangular one
+    Ordered(Option<Superstructure>),
+    //ordered without a single dominant sublattice
+    Other,
+}
+
+impl Phase {
+    //classifies a point from its sorted mean sublattice fractions, the last one being the least occupied sublattice
+    pub fn classify(record: &PointRecord) -> Phase {
+        let least = record.order[record.order.len() - 1];
+        if record.order[0] - least < ORDER_THRESHOLD {
+            return Phase::Disordered;
+        }
+        if record.order[0] - record.order[1] > record.order[1] - least {
+            let parameters = record.parameters;
+            return Phase::Ordered(Superstructure::measured(parameters.lattice, parameters.fill, parameters.superstructure));
+        }
+        return Phase::Other;
+    }
+
+    pub fn index(&self) -> usize {
+        return match self {
+            Phase::Disordered => 0,
+            Phase::Ordered(None) => 1,
+            Phase::Ordered(Some(superstructure)) => 2 + Superstructure::all().iter().position(|s| s == superstructure).unwrap_or(0),
+            Phase::Other => 2 + Superstructure::all().len(),
+        };
+    }
+
+    pub fn color(&self) -> RGBColor {
+        return match self {
+            Phase::Disordered => RGBColor(230, 230, 230),
+            Phase::Ordered(None) => RGBColor(90, 90, 90),
+            Phase::Ordered(Some(Superstructure::Sqrt3)) => RGBColor(40, 90, 200),
+            Phase::Ordered(Some(Superstructure::Sqrt3Complement)) => RGBColor(130, 60, 180),
+            Phase::Ordered(Some(Superstructure::TwoByTwo)) => RGBColor(40, 160, 90),
+            Phase::Ordered(Some(Superstructure::Sqrt7)) => RGBColor(200, 50, 60),
+            Phase::Other => RGBColor(220, 120, 30),
+        };
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Phase::Disordered => write!(f, "disordered"),
+            Phase::Ordered(None) => write!(f, "sublattice ordered"),
+            Phase::Ordered(Some(superstructure)) => write!(f, "{} ordered", superstructure),
+            Phase::Other => write!(f, "other ordered"),
+        };
+    }
+}
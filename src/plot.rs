@@ -1,3 +1,5 @@
+use crate::error::Result;
+
 use std::ops::Range;
 use plotters::prelude::*;
 
@@ -16,11 +18,11 @@ impl Plot {
 
 
     //plots a timeseries stored as a Vec<(i32, f64)>
-    pub fn plot_multiple_timeseries(&self, path: &str, caption: &str, data: Vec<Vec<(i32, f64)>>, color_start: &RGBColor, color_end: &RGBColor, labels: Vec<String>) {
+    pub fn plot_multiple_timeseries(&self, path: &str, caption: &str, data: Vec<Vec<(i32, f64)>>, color_start: &RGBColor, color_end: &RGBColor, labels: Vec<String>) -> Result<()> {
 
         //initialize image
         let backend = SVGBackend::new(path , (self.x_size, self.y_size)).into_drawing_area();
-        backend.fill(&WHITE).unwrap();
+        backend.fill(&WHITE)?;
 
         //initialize context of image
         let mut chartbuilder = ChartBuilder::on(&backend);
@@ -31,17 +33,16 @@ impl Plot {
         let mut chartcontext = chartbuilder.build_cartesian_2d(
             self.x_range.clone(), 
             self.y_range.clone()
-        ).unwrap();
+        )?;
 
-        chartcontext.configure_mesh().draw().unwrap();
+        chartcontext.configure_mesh().draw()?;
 
         //plot timeseries
         let gradient: Vec<RGBColor> = self.get_gradient(color_start, color_end, data.len());
         for plot_id in 0..data.len() {
-            let grad = gradient[plot_id].clone();
+            let grad = gradient[plot_id];
             chartcontext
-                .draw_series(LineSeries::new(data[plot_id].clone(), gradient[plot_id]))
-                .unwrap()
+                .draw_series(LineSeries::new(data[plot_id].clone(), gradient[plot_id]))?
                 .label(labels[plot_id].to_string())
                 .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], grad));
         }
@@ -49,16 +50,19 @@ impl Plot {
         //draw labels
         chartcontext
         .configure_series_labels()
-        .border_style(&BLACK)
+        .border_style(BLACK)
         .position(SeriesLabelPosition::UpperLeft)
-        .draw().unwrap();
+        .draw()?;
+
+        backend.present()?;
+        return Ok(());
     }
 
-    pub fn plot_multiple_orders(&self, path: &str, caption: &str, data: Vec<Vec<Vec<(i32, f64)>>>, color_start: &RGBColor, color_end: &RGBColor, labels: Vec<String>) {
+    pub fn plot_multiple_orders(&self, path: &str, caption: &str, data: Vec<Vec<Vec<(i32, f64)>>>, color_start: &RGBColor, color_end: &RGBColor, labels: Vec<String>) -> Result<()> {
 
         //initialize image
         let backend = SVGBackend::new(path , (self.x_size, self.y_size)).into_drawing_area();
-        backend.fill(&WHITE).unwrap();
+        backend.fill(&WHITE)?;
 
         //initialize context of image
         let mut chartbuilder = ChartBuilder::on(&backend);
@@ -69,18 +73,17 @@ impl Plot {
         let mut chartcontext = chartbuilder.build_cartesian_2d(
             self.x_range.clone(), 
             self.y_range.clone()
-        ).unwrap();
+        )?;
 
-        chartcontext.configure_mesh().draw().unwrap();
+        chartcontext.configure_mesh().draw()?;
 
         //plot timeseries
         let gradient: Vec<RGBColor> = self.get_gradient(color_start, color_end, data.len());
-        for plot_id in 0..data.len() {
-            for order_value in 0..3 {
-                let grad = gradient[plot_id].clone();
+        for (plot_id, series) in data.iter().enumerate() {
+            for values in series {
+                let grad = gradient[plot_id];
                 chartcontext
-                    .draw_series(LineSeries::new(data[plot_id][order_value].clone(), gradient[plot_id]))
-                    .unwrap()
+                    .draw_series(LineSeries::new(values.clone(), gradient[plot_id]))?
                     .label(labels[plot_id].to_string())
                     .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], grad));
             }
@@ -89,16 +92,19 @@ impl Plot {
         //draw labels
         chartcontext
         .configure_series_labels()
-        .border_style(&BLACK)
+        .border_style(BLACK)
         .position(SeriesLabelPosition::UpperLeft)
-        .draw().unwrap();
+        .draw()?;
+
+        backend.present()?;
+        return Ok(());
     }
 
-    pub fn plot_timeseries(&self, path: &str, caption: &str, data: Vec<(i32, f64)>, color: &RGBColor, label: &str) {
+    pub fn plot_timeseries(&self, path: &str, caption: &str, data: Vec<(i32, f64)>, color: &RGBColor, label: &str) -> Result<()> {
 
         //initialize image
         let backend = SVGBackend::new(path , (self.x_size, self.y_size)).into_drawing_area();
-        backend.fill(&WHITE).unwrap();
+        backend.fill(&WHITE)?;
 
         //initialize context of image
         let mut chartbuilder = ChartBuilder::on(&backend);
@@ -109,29 +115,242 @@ impl Plot {
         let mut chartcontext = chartbuilder.build_cartesian_2d(
             self.x_range.clone(), 
             self.y_range.clone()
-        ).unwrap();
+        )?;
 
-        chartcontext.configure_mesh().draw().unwrap();
+        chartcontext.configure_mesh().draw()?;
 
         //plot timeseries
 
         chartcontext.draw_series(LineSeries::new(
             data,
             &color,
-        )).unwrap()
+        ))?
         .label(label.to_string())
         .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
 
         //draw labels
         chartcontext
         .configure_series_labels()
-        .border_style(&BLACK)
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::UpperLeft)
+        .draw()?;
+
+        backend.present()?;
+        return Ok(());
+    }
+
+    //plots mean values with their error bars against a parameter, data is stored as (x, mean, error)
+    pub fn plot_errorbars(&self, path: &str, caption: &str, x_range: Range<f64>, data: Vec<(f64, f64, f64)>, color: &RGBColor, label: &str) -> Result<()> {
+
+        //initialize image
+        let backend = SVGBackend::new(path , (self.x_size, self.y_size)).into_drawing_area();
+        backend.fill(&WHITE)?;
+
+        //initialize context of image
+        let mut chartbuilder = ChartBuilder::on(&backend);
+        chartbuilder.caption(caption, ("Arial", 30))
+        .set_all_label_area_size(40);
+
+        //draw cartesian plane
+        let mut chartcontext = chartbuilder.build_cartesian_2d(
+            x_range,
+            self.y_range.clone()
+        )?;
+
+        chartcontext.configure_mesh().draw()?;
+
+        //plot points with their error bars
+        chartcontext.draw_series(data.iter().map(|&(x, mean, error)| {
+            ErrorBar::new_vertical(x, mean - error, mean, mean + error, color.filled(), 6)
+        }))?
+        .label(label.to_string())
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+
+        chartcontext.draw_series(LineSeries::new(
+            data.iter().map(|&(x, mean, _)| (x, mean)),
+            &color,
+        ))?;
+
+        //draw labels
+        chartcontext
+        .configure_series_labels()
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::UpperLeft)
+        .draw()?;
+
+        backend.present()?;
+        return Ok(());
+    }
+
+    //plots a histogram as bars, the bins are stored as (lower edge, upper edge, height)
+    pub fn plot_histogram(&self, path: &str, caption: &str, x_range: Range<f64>, data: Vec<(f64, f64, f64)>, color: &RGBColor, label: &str) -> Result<()> {
+
+        //initialize image
+        let backend = SVGBackend::new(path , (self.x_size, self.y_size)).into_drawing_area();
+        backend.fill(&WHITE)?;
+
+        //initialize context of image
+        let mut chartbuilder = ChartBuilder::on(&backend);
+        chartbuilder.caption(caption, ("Arial", 30))
+        .set_all_label_area_size(40);
+
+        //draw cartesian plane
+        let mut chartcontext = chartbuilder.build_cartesian_2d(
+            x_range,
+            self.y_range.clone()
+        )?;
+
+        chartcontext.configure_mesh().draw()?;
+
+        //plot a bar per bin with its outline
+        chartcontext.draw_series(data.iter().map(|&(lower, upper, height)| {
+            Rectangle::new([(lower, 0.0), (upper, height)], color.mix(0.5).filled())
+        }))?
+        .label(label.to_string())
+        .legend(|(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.mix(0.5).filled()));
+
+        chartcontext.draw_series(data.iter().map(|&(lower, upper, height)| {
+            Rectangle::new([(lower, 0.0), (upper, height)], color)
+        }))?;
+
+        //draw labels
+        chartcontext
+        .configure_series_labels()
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::UpperLeft)
+        .draw()?;
+
+        backend.present()?;
+        return Ok(());
+    }
+
+    //plots multiple timeseries stored as (x, mean, error) with a shaded band of one error around the mean
+    pub fn plot_confidence_bands(&self, path: &str, caption: &str, data: Vec<Vec<(i32, f64, f64)>>, color_start: &RGBColor, color_end: &RGBColor, labels: Vec<String>) -> Result<()> {
+
+        //initialize image
+        let backend = SVGBackend::new(path , (self.x_size, self.y_size)).into_drawing_area();
+        backend.fill(&WHITE)?;
+
+        //initialize context of image
+        let mut chartbuilder = ChartBuilder::on(&backend);
+        chartbuilder.caption(caption, ("Arial", 30))
+        .set_all_label_area_size(40);
+
+        //draw cartesian plane
+        let mut chartcontext = chartbuilder.build_cartesian_2d(
+            self.x_range.clone(),
+            self.y_range.clone()
+        )?;
+
+        chartcontext.configure_mesh().draw()?;
+
+        //plot bands and their mean
+        let gradient: Vec<RGBColor> = self.get_gradient(color_start, color_end, data.len());
+        for plot_id in 0..data.len() {
+            let grad = gradient[plot_id];
+            let mut band: Vec<(i32, f64)> = data[plot_id].iter().map(|&(x, mean, error)| (x, mean + error)).collect();
+            band.extend(data[plot_id].iter().rev().map(|&(x, mean, error)| (x, mean - error)));
+            chartcontext
+                .draw_series(std::iter::once(Polygon::new(band, grad.mix(0.2).filled())))?;
+            chartcontext
+                .draw_series(LineSeries::new(data[plot_id].iter().map(|&(x, mean, _)| (x, mean)), grad))?
+                .label(labels[plot_id].to_string())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], grad));
+        }
+
+        //draw labels
+        chartcontext
+        .configure_series_labels()
+        .border_style(BLACK)
         .position(SeriesLabelPosition::UpperLeft)
-        .draw().unwrap();
+        .draw()?;
+
+        backend.present()?;
+        return Ok(());
     }
 
-    //generates a gradient between two colors in rgb space
+    //plots a grid of coloured cells centred on x_values and y_values, colors is indexed as [x][y].
+    //a black line is drawn between neighbouring cells that belong to different categories
+    pub fn plot_heatmap(&self, path: &str, caption: &str, x_values: &[f64], y_values: &[f64], colors: Vec<Vec<RGBColor>>, categories: &[Vec<usize>]) -> Result<()> {
+
+        let x_edges = self.get_cell_edges(x_values);
+        let y_edges = self.get_cell_edges(y_values);
+
+        //initialize image
+        let backend = SVGBackend::new(path , (self.x_size, self.y_size)).into_drawing_area();
+        backend.fill(&WHITE)?;
+
+        //initialize context of image
+        let mut chartbuilder = ChartBuilder::on(&backend);
+        chartbuilder.caption(caption, ("Arial", 30))
+        .set_all_label_area_size(40);
+
+        //draw cartesian plane
+        let mut chartcontext = chartbuilder.build_cartesian_2d(
+            x_edges[0]..x_edges[x_edges.len() - 1],
+            y_edges[0]..y_edges[y_edges.len() - 1]
+        )?;
+
+        chartcontext.configure_mesh().disable_mesh().draw()?;
+
+        //draw cells
+        for x in 0..x_values.len() {
+            chartcontext.draw_series((0..y_values.len()).map(|y| {
+                Rectangle::new([(x_edges[x], y_edges[y]), (x_edges[x + 1], y_edges[y + 1])], colors[x][y].filled())
+            }))?;
+        }
+
+        //draw boundaries between categories
+        let mut boundaries: Vec<Vec<(f64, f64)>> = vec![];
+        for x in 0..x_values.len() {
+            for y in 0..y_values.len() {
+                if x + 1 < x_values.len() && categories[x][y] != categories[x + 1][y] {
+                    boundaries.push(vec![(x_edges[x + 1], y_edges[y]), (x_edges[x + 1], y_edges[y + 1])]);
+                }
+                if y + 1 < y_values.len() && categories[x][y] != categories[x][y + 1] {
+                    boundaries.push(vec![(x_edges[x], y_edges[y + 1]), (x_edges[x + 1], y_edges[y + 1])]);
+                }
+            }
+        }
+        for boundary in boundaries {
+            chartcontext.draw_series(LineSeries::new(boundary, BLACK.stroke_width(3)))?;
+        }
+
+        backend.present()?;
+        return Ok(());
+    }
+
+    //maps values linearly between their minimum and maximum onto a gradient from color_start to color_end
+    pub fn get_colormap(&self, values: &[Vec<f64>], color_start: &RGBColor, color_end: &RGBColor) -> Vec<Vec<RGBColor>> {
+        let min = values.iter().flatten().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().flatten().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let (r1, g1, b1) = color_start.rgb();
+        let (r2, g2, b2) = color_end.rgb();
+        let mix = |a: u8, b: u8, t: f64| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        return values.iter().map(|row| row.iter().map(|&value| {
+            let t = if max > min { (value - min) / (max - min) } else { 0.0 };
+            RGBColor(mix(r1, r2, t), mix(g1, g2, t), mix(b1, b2, t))
+        }).collect()).collect();
+    }
+
+    //edges of the cells centred on values, halfway between neighbouring centres
+    fn get_cell_edges(&self, values: &[f64]) -> Vec<f64> {
+        if values.len() == 1 {
+            return vec![values[0] - 0.5, values[0] + 0.5];
+        }
+        let mut edges = vec![values[0] - (values[1] - values[0]) / 2.0];
+        for i in 0..values.len() - 1 {
+            edges.push((values[i] + values[i + 1]) / 2.0);
+        }
+        edges.push(values[values.len() - 1] + (values[values.len() - 1] - values[values.len() - 2]) / 2.0);
+        return edges;
+    }
+
+    //generates a gradient between two colors in rgb space, a single step is just the start color
     pub fn get_gradient(&self, start_color: &RGBColor, end_color: &RGBColor, steps: usize) -> Vec<RGBColor> {
+        if steps <= 1 {
+            return vec![*start_color; steps];
+        }
         let (r1, g1, b1) = start_color.rgb();
         let (r2, g2, b2) = end_color.rgb();
     
@@ -0,0 +1,245 @@
+use rand::RngCore;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use rand_pcg::Pcg64;
+use std::fmt;
+
+//the algorithms a board can draw its random numbers from. both come from crates pinned to an exact version in Cargo.toml,
+//unlike StdRng whose algorithm may change between versions of rand, so a recorded seed and stream always give the same run
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Backend {
+    //ChaCha with 12 rounds from rand_chacha 0.3.1, the default
+    ChaCha12,
+    //PCG XSL RR 128/64 from rand_pcg 0.3.1, with a smaller state and faster to draw from
+    Pcg64,
+}
+
+impl Backend {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Backend::ChaCha12 => "chacha12",
+            Backend::Pcg64 => "pcg64",
+        };
+    }
+
+    pub fn by_name(name: &str) -> Option<Backend> {
+        return [Backend::ChaCha12, Backend::Pcg64].into_iter().find(|backend| backend.name() == name);
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.name());
+    }
+}
+
+//SplitMix64, expands a 64 bit seed into well mixed words
+fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return z ^ (z >> 31);
+}
+
+//chacha12 is kept inline, a box would cost an indirection on every draw
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+enum Core {
+    ChaCha12(ChaCha12Rng),
+    //the generator and the number of 64 bit steps it has taken
+    Pcg64(Pcg64, u128),
+}
+
+//a random generator on one of the streams of a seed. streams of the same seed are independent sequences, so a batch splits
+//a single seed into a stream per replica and a parallel sweep into a stream per block.
+//chacha12 is keyed with SeedableRng::seed_from_u64(seed) and runs on its 64 bit stream, so stream 0 is the generator
+//ChaCha12Rng::seed_from_u64(seed). pcg64 takes its 128 bit state from two SplitMix64 words of the seed, the low one offset
+//by a SplitMix64 word of the stream, and its increment from the stream
+#[derive(Clone)]
+pub struct Generator {
+    core: Core,
+    seed: u64,
+    stream: u64,
+}
+
+impl Generator {
+    pub fn new(backend: Backend, seed: u64, stream: u64) -> Self {
+        let core = match backend {
+            Backend::ChaCha12 => {
+                let mut rng = ChaCha12Rng::seed_from_u64(seed);
+                rng.set_stream(stream);
+                Core::ChaCha12(rng)
+            }
+            Backend::Pcg64 => {
+                let (mut words, mut offset) = (seed, stream);
+                let high = splitmix(&mut words) as u128;
+                let low = (splitmix(&mut words) ^ splitmix(&mut offset)) as u128;
+                Core::Pcg64(Pcg64::new(high << 64 | low, stream as u128), 0)
+            }
+        };
+        return Generator{core, seed, stream};
+    }
+
+    //the generator of backend, seed and stream after it has advanced to position
+    pub fn at(backend: Backend, seed: u64, stream: u64, position: u128) -> Self {
+        let mut generator = Generator::new(backend, seed, stream);
+        match &mut generator.core {
+            Core::ChaCha12(rng) => rng.set_word_pos(position),
+            Core::Pcg64(rng, steps) => {
+                rng.advance(position);
+                *steps = position;
+            }
+        }
+        return generator;
+    }
+
+    pub fn backend(&self) -> Backend {
+        return match self.core {
+            Core::ChaCha12(_) => Backend::ChaCha12,
+            Core::Pcg64(..) => Backend::Pcg64,
+        };
+    }
+
+    pub fn seed(&self) -> u64 {
+        return self.seed;
+    }
+
+    pub fn stream(&self) -> u64 {
+        return self.stream;
+    }
+
+    //how far the generator has advanced along its stream: the 32 bit words drawn by chacha12, the steps taken by pcg64
+    pub fn position(&self) -> u128 {
+        return match &self.core {
+            Core::ChaCha12(rng) => rng.get_word_pos(),
+            Core::Pcg64(_, steps) => *steps,
+        };
+    }
+
+}
+
+impl RngCore for Generator {
+    fn next_u32(&mut self) -> u32 {
+        return match &mut self.core {
+            Core::ChaCha12(rng) => rng.next_u32(),
+            Core::Pcg64(rng, steps) => {
+                *steps += 1;
+                rng.next_u32()
+            }
+        };
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        return match &mut self.core {
+            Core::ChaCha12(rng) => rng.next_u64(),
+            Core::Pcg64(rng, steps) => {
+                *steps += 1;
+                rng.next_u64()
+            }
+        };
+    }
+
+    //pcg64 fills 8 bytes a step, so the steps stay counted
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match &mut self.core {
+            Core::ChaCha12(rng) => rng.fill_bytes(dest),
+            Core::Pcg64(..) => {
+                for chunk in dest.chunks_mut(8) {
+                    let bytes = self.next_u64().to_le_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+            }
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        return Ok(());
+    }
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{} seed {} stream {}", self.backend(), self.seed, self.stream);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint;
+    use crate::hexboard::HexBoard;
+    use rand::Rng;
+
+    #[test]
+    fn stream_zero_of_chacha12_is_the_seeded_generator() {
+        let mut generator = Generator::new(Backend::ChaCha12, 3463462432, 0);
+        let mut rng = ChaCha12Rng::seed_from_u64(3463462432);
+        assert!((0..100).all(|_| generator.next_u64() == rng.next_u64()));
+    }
+
+    //the first draws of every backend, which must never change or archived seeds stop reproducing their runs
+    #[test]
+    fn draws_are_stable() {
+        let draws = [
+            (Backend::ChaCha12, [18398896560331411741, 15071531305834398130, 12447705563529086961]),
+            (Backend::Pcg64, [8238183008206013968, 6808753489792891023, 2001681458609513017]),
+        ];
+        for (backend, expected) in draws {
+            let mut generator = Generator::new(backend, 42, 7);
+            let first: Vec<u64> = (0..3).map(|_| generator.next_u64()).collect();
+            assert_eq!(first, expected, "{}", backend);
+        }
+    }
+
+    #[test]
+    fn streams_differ() {
+        for backend in [Backend::ChaCha12, Backend::Pcg64] {
+            let firsts: Vec<u64> = (0..64).map(|stream| Generator::new(backend, 1, stream).next_u64()).collect();
+            assert!(firsts.iter().enumerate().all(|(i, a)| firsts[i + 1..].iter().all(|b| a != b)), "{}", backend);
+        }
+    }
+
+    //a generator rebuilt at the position of another continues with the same draws, whatever mix of draws got it there
+    #[test]
+    fn generators_resume_at_their_position() {
+        for backend in [Backend::ChaCha12, Backend::Pcg64] {
+            let mut generator = Generator::new(backend, 5, 3);
+            let mut bytes = [0u8; 13];
+            for i in 0..1000 {
+                match i % 4 {
+                    0 => { generator.next_u32(); }
+                    1 => { generator.gen_range(0..17); }
+                    2 => generator.fill_bytes(&mut bytes),
+                    _ => { generator.gen::<f64>(); }
+                }
+            }
+            let mut resumed = Generator::at(backend, 5, 3, generator.position());
+            assert!((0..100).all(|_| generator.next_u64() == resumed.next_u64()), "{}", backend);
+        }
+    }
+
+    //a board saved to a checkpoint and loaded back runs on exactly as the original, on either backend
+    #[test]
+    fn checkpoints_keep_the_generator() {
+        for backend in [Backend::ChaCha12, Backend::Pcg64] {
+            let mut board = HexBoard::new(12, Generator::new(backend, 9, 4), 2.0, 1.0 / 3.0);
+            board.initialize();
+            for _ in 0..5000 {
+                board.advance_timestep_repulsive();
+            }
+            let path = std::env::temp_dir().join(format!("tesina_mfs_generator_{}_{}.ckpt", backend, std::process::id()));
+            checkpoint::save(&board, path.to_str().unwrap()).unwrap();
+            let mut loaded = checkpoint::load(path.to_str().unwrap()).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!((loaded.rng.backend(), loaded.rng.seed(), loaded.rng.stream()), (backend, 9, 4));
+            for _ in 0..5000 {
+                board.advance_timestep_repulsive();
+                loaded.advance_timestep_repulsive();
+            }
+            assert!(board.grid.hexes().zip(loaded.grid.hexes()).all(|(a, b)| a.value == b.value), "{}", backend);
+            assert_eq!(board.rng.next_u64(), loaded.rng.next_u64(), "{}", backend);
+        }
+    }
+}
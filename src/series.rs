@@ -0,0 +1,208 @@
+use crate::HexBoard;
+use crate::hexboard::Boundary;
+use crate::sweep::Parameters;
+use crate::superstructure::Superstructure;
+use crate::random::Generator;
+use crate::error::Result;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, prelude::*, BufWriter};
+
+//file formats the recorded timeseries can be written in
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    //comma separated values with a header row of column names
+    Csv,
+    //one json object per row, keyed by column name
+    JsonLines,
+    //a two dimensional little endian f64 numpy array, one column per observable
+    Npy,
+}
+
+impl Format {
+    pub fn extension(&self) -> &str {
+        return match self {
+            Format::Csv => "csv",
+            Format::JsonLines => "jsonl",
+            Format::Npy => "npy",
+        };
+    }
+}
+
+//observables recorded during a run, one row per sample, with the parameters that produced them.
+//the parameters and column names are written to a json sidecar next to the data, whatever its format
+pub struct Timeseries {
+    metadata: Vec<(String, String)>,
+    columns: Vec<String>,
+    steps: Vec<i64>,
+    rows: Vec<Vec<f64>>,
+}
+
+impl Timeseries {
+    //the first column is always the step of the sample, followed by columns
+    pub fn new(columns: &[&str]) -> Self {
+        return Timeseries{metadata: vec![], columns: columns.iter().map(|c| c.to_string()).collect(), steps: vec![], rows: vec![]};
+    }
+
+    //adds an entry to the parameter header
+    pub fn set(&mut self, key: &str, value: impl fmt::Display) {
+        self.metadata.push((key.to_string(), value.to_string()));
+    }
+
+    //adds the parameters of a sweep point to the header
+    pub fn set_parameters(&mut self, parameters: &Parameters) {
+        self.set("size", parameters.size);
+        self.set("fill", parameters.fill);
+        self.set("betaj", parameters.betaj);
+        self.set("coupling", parameters.coupling);
+        self.set("field", parameters.field);
+        self.set("boundary", boundary_name(parameters.boundary));
+        self.set("initial", parameters.initial);
+        self.set("lattice", parameters.lattice.name());
+        if let Some(superstructure) = Superstructure::measured(parameters.lattice, parameters.fill, parameters.superstructure) {
+            self.set("superstructure", superstructure.name());
+        }
+        if !parameters.substrate.is_clean() {
+            self.set("substrate", parameters.substrate);
+        }
+    }
+
+    //adds the backend, seed and stream of a random generator to the header
+    pub fn set_generator(&mut self, rng: &Generator) {
+        self.set("generator", rng.backend());
+        self.set("seed", rng.seed());
+        self.set("stream", rng.stream());
+    }
+
+    //adds the parameters of a board and its random generator to the header
+    pub fn set_board(&mut self, board: &HexBoard) {
+        self.set("size", board.size);
+        self.set("fill", board.fillrate);
+        self.set("betaj", board.disorder);
+        self.set("coupling", board.coupling);
+        self.set("field", board.field);
+        self.set("boundary", boundary_name(board.boundary));
+        self.set("lattice", board.lattice.name());
+        if let Some(superstructure) = board.get_superstructure() {
+            self.set("superstructure", superstructure.name());
+        }
+        if let Some(realisation) = board.substrate.as_ref() {
+            self.set("substrate", realisation.substrate);
+            self.set("substrate seed", realisation.seed);
+        }
+        self.set_generator(&board.rng);
+    }
+
+    //appends a sample, values must follow the order of the columns
+    pub fn push(&mut self, step: i64, values: Vec<f64>) {
+        self.rows.push(values);
+        self.steps.push(step);
+    }
+
+    //writes the samples to "{stem}.{extension}" and the header to "{stem}.json"
+    pub fn write(&self, stem: &str, format: Format) -> Result<()> {
+        let mut file = BufWriter::new(File::create(format!("{}.{}", stem, format.extension()))?);
+        match format {
+            Format::Csv => self.write_csv(&mut file)?,
+            Format::JsonLines => self.write_json_lines(&mut file)?,
+            Format::Npy => self.write_npy(&mut file)?,
+        }
+        file.flush()?;
+        self.write_sidecar(&format!("{}.json", stem), format)?;
+        return Ok(());
+    }
+
+    fn write_csv(&self, file: &mut impl Write) -> io::Result<()> {
+        writeln!(file, "step,{}", self.columns.join(","))?;
+        for (step, row) in self.steps.iter().zip(self.rows.iter()) {
+            let values: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+            writeln!(file, "{},{}", step, values.join(","))?;
+        }
+        return Ok(());
+    }
+
+    fn write_json_lines(&self, file: &mut impl Write) -> io::Result<()> {
+        for (step, row) in self.steps.iter().zip(self.rows.iter()) {
+            let mut fields = vec![format!("\"step\":{}", step)];
+            for (column, value) in self.columns.iter().zip(row.iter()) {
+                fields.push(format!("{}:{}", json_string(column), json_number(*value)));
+            }
+            writeln!(file, "{{{}}}", fields.join(","))?;
+        }
+        return Ok(());
+    }
+
+    //npy version 1.0: magic, header length, a python dict literal padded so the data starts on a 64 byte boundary
+    fn write_npy(&self, file: &mut impl Write) -> io::Result<()> {
+        let mut header = format!(
+            "{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, {}), }}",
+            self.rows.len(), self.columns.len() + 1,
+        );
+        let unpadded = 10 + header.len() + 1;
+        header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+        header.push('\n');
+        file.write_all(b"\x93NUMPY\x01\x00")?;
+        file.write_all(&(header.len() as u16).to_le_bytes())?;
+        file.write_all(header.as_bytes())?;
+        for (step, row) in self.steps.iter().zip(self.rows.iter()) {
+            file.write_all(&(*step as f64).to_le_bytes())?;
+            for value in row {
+                file.write_all(&value.to_le_bytes())?;
+            }
+        }
+        return Ok(());
+    }
+
+    fn write_sidecar(&self, path: &str, format: Format) -> io::Result<()> {
+        let parameters: Vec<String> = self.metadata.iter().map(|(key, value)| format!("{}: {}", json_string(key), json_value(value))).collect();
+        let mut columns = vec![json_string("step")];
+        columns.extend(self.columns.iter().map(|column| json_string(column)));
+        let mut file = File::create(path)?;
+        writeln!(file, "{{")?;
+        writeln!(file, "  \"format\": {},", json_string(format.extension()))?;
+        writeln!(file, "  \"rows\": {},", self.rows.len())?;
+        writeln!(file, "  \"columns\": [{}],", columns.join(", "))?;
+        writeln!(file, "  \"parameters\": {{{}}}", parameters.join(", "))?;
+        writeln!(file, "}}")?;
+        return Ok(());
+    }
+}
+
+fn boundary_name(boundary: Boundary) -> &'static str {
+    return match boundary {
+        Boundary::Periodic => "periodic",
+        Boundary::Open => "open",
+    };
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    return escaped;
+}
+
+//json has no nan or infinity, they are written as null
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        return value.to_string();
+    }
+    return "null".to_string();
+}
+
+//header values that read as finite numbers are written as numbers, the others as strings
+fn json_value(value: &str) -> String {
+    return match value.parse::<f64>() {
+        Ok(number) if number.is_finite() => value.to_string(),
+        _ => json_string(value),
+    };
+}
@@ -0,0 +1,282 @@
+use crate::random::{Backend, Generator};
+
+use rand::Rng;
+use std::collections::BTreeMap;
+
+//window factor of the automatic windowing method, the sum of the autocorrelation stops at the first lag W >= C * tau(W)
+const WINDOW_FACTOR: f64 = 5.0;
+
+pub fn mean(data: &[f64]) -> f64 {
+    return data.iter().sum::<f64>() / data.len() as f64;
+}
+
+//variance of data around its mean
+pub fn variance(data: &[f64]) -> f64 {
+    let average = mean(data);
+    return data.iter().map(|x| (x - average).powi(2)).sum::<f64>() / data.len() as f64;
+}
+
+//normalized autocorrelation of data at a given lag, given the mean and variance of data so a sum over lags computes them once
+fn autocorrelation(data: &[f64], lag: usize, average: f64, variance: f64) -> f64 {
+    if variance == 0.0 || lag >= data.len() {
+        return 0.0;
+    }
+    let mut covariance = 0.0;
+    for i in 0..data.len() - lag {
+        covariance += (data[i] - average) * (data[i + lag] - average);
+    }
+    return covariance / (data.len() - lag) as f64 / variance;
+}
+
+//integrated autocorrelation time of data in units of samples, estimated with the windowing method (Sokal).
+//uncorrelated data gives 0.5
+pub fn integrated_autocorrelation_time(data: &[f64]) -> f64 {
+    let (average, variance) = (mean(data), variance(data));
+    let mut tau = 0.5;
+    for lag in 1..data.len() {
+        tau += autocorrelation(data, lag, average, variance);
+        if lag as f64 >= WINDOW_FACTOR * tau {
+            break;
+        }
+    }
+    return tau.max(0.5);
+}
+
+//number of samples to skip between two kept samples so that they are roughly independent
+pub fn thinning_interval(tau: f64) -> usize {
+    return ((2.0 * tau).ceil() as usize).max(1);
+}
+
+//keeps one sample every 2 tau samples
+pub fn thin<T: Clone>(data: &[T], tau: f64) -> Vec<T> {
+    return data.iter().step_by(thinning_interval(tau)).cloned().collect();
+}
+
+//number of independent samples contained in a correlated series
+pub fn effective_sample_size(samples: usize, tau: f64) -> f64 {
+    return samples as f64 / (2.0 * tau);
+}
+
+//autocorrelation time, effective sample size and mean of the thinned samples of an observable
+pub struct Sampling {
+    pub tau: f64,
+    pub effective_samples: f64,
+    pub samples: Vec<f64>,
+}
+
+impl Sampling {
+    pub fn new(data: &[f64]) -> Self {
+        let tau = integrated_autocorrelation_time(data);
+        return Sampling{tau, effective_samples: effective_sample_size(data.len(), tau), samples: thin(data, tau)};
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        return mean(&self.samples);
+    }
+}
+
+//mean of an observable with its statistical error
+#[derive(Clone, Copy)]
+pub struct Estimate {
+    pub mean: f64,
+    pub error: f64,
+}
+
+impl Estimate {
+    //combines the estimates of independent replicas of the same parameter point. the error is the larger of the one
+    //propagated from the replica errors and the standard error of the replica means, so replicas that disagree by more
+    //than their own errors widen it
+    pub fn combine(estimates: &[Estimate]) -> Estimate {
+        let replicas = estimates.len() as f64;
+        let means: Vec<f64> = estimates.iter().map(|e| e.mean).collect();
+        let mean = mean(&means);
+        let propagated = estimates.iter().map(|e| e.error * e.error).sum::<f64>().sqrt() / replicas;
+        let mut scatter = 0.0;
+        if estimates.len() > 1 {
+            scatter = (means.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (replicas - 1.0) / replicas).sqrt();
+        }
+        return Estimate{mean, error: propagated.max(scatter)};
+    }
+
+    //multiplies the estimate and its error by a constant factor
+    pub fn scale(&self, factor: f64) -> Estimate {
+        return Estimate{mean: self.mean * factor, error: self.error * factor.abs()};
+    }
+}
+
+//how the error of the mean of a correlated series is computed
+#[derive(Clone, Copy)]
+pub enum ErrorMethod {
+    //Flyvbjerg-Petersen blocking
+    Blocking,
+    //jackknife over contiguous blocks
+    Jackknife { blocks: usize },
+    //bootstrap of the samples thinned by their autocorrelation time
+    Bootstrap { resamples: usize, seed: u64 },
+}
+
+impl ErrorMethod {
+    pub fn estimate(&self, data: &[f64]) -> Estimate {
+        return match *self {
+            ErrorMethod::Blocking => Estimate{mean: mean(data), error: blocking_error(data)},
+            ErrorMethod::Jackknife { blocks } => jackknife(data, blocks, mean),
+            ErrorMethod::Bootstrap { resamples, seed } => bootstrap(data, resamples, seed, mean),
+        };
+    }
+
+    //estimate of any function of the samples, such as a variance. blocking only applies to means,
+    //so it falls back to a jackknife over 16 blocks
+    pub fn estimate_with(&self, data: &[f64], estimator: fn(&[f64]) -> f64) -> Estimate {
+        return match *self {
+            ErrorMethod::Blocking => jackknife(data, 16, estimator),
+            ErrorMethod::Jackknife { blocks } => jackknife(data, blocks, estimator),
+            ErrorMethod::Bootstrap { resamples, seed } => bootstrap(data, resamples, seed, estimator),
+        };
+    }
+}
+
+
+//error of the mean from repeatedly averaging pairs of samples, the largest estimate
+//over the blocking levels with at least 8 blocks is taken as the plateau value
+pub fn blocking_error(data: &[f64]) -> f64 {
+    let mut blocked = data.to_vec();
+    let mut error: f64 = 0.0;
+    while blocked.len() >= 8 {
+        let n = blocked.len() as f64;
+        let average = mean(&blocked);
+        let variance = blocked.iter().map(|x| (x - average).powi(2)).sum::<f64>() / n;
+        error = error.max((variance / (n - 1.0)).sqrt());
+        blocked = blocked.chunks_exact(2).map(|pair| (pair[0] + pair[1]) / 2.0).collect();
+    }
+    return error;
+}
+
+//jackknife estimate of estimator over data, leaving out one contiguous block at a time
+pub fn jackknife(data: &[f64], blocks: usize, estimator: fn(&[f64]) -> f64) -> Estimate {
+    let blocks = blocks.min(data.len());
+    if blocks < 2 {
+        return Estimate{mean: estimator(data), error: 0.0};
+    }
+    let block_size = data.len() / blocks;
+    let mut values: Vec<f64> = vec![];
+    for block in 0..blocks {
+        let mut rest: Vec<f64> = data[..block * block_size].to_vec();
+        rest.extend_from_slice(&data[(block + 1) * block_size..]);
+        values.push(estimator(&rest));
+    }
+    let n = blocks as f64;
+    let average = mean(&values);
+    let variance = values.iter().map(|x| (x - average).powi(2)).sum::<f64>() * (n - 1.0) / n;
+    return Estimate{mean: estimator(data), error: variance.sqrt()};
+}
+
+//bootstrap estimate of estimator over data, resampling the samples left after thinning by the autocorrelation time
+pub fn bootstrap(data: &[f64], resamples: usize, seed: u64, estimator: fn(&[f64]) -> f64) -> Estimate {
+    let samples = thin(data, integrated_autocorrelation_time(data));
+    if samples.len() < 2 || resamples < 2 {
+        return Estimate{mean: estimator(data), error: 0.0};
+    }
+    let mut rng = Generator::new(Backend::ChaCha12, seed, 0);
+    let mut values: Vec<f64> = vec![];
+    for _ in 0..resamples {
+        let resample: Vec<f64> = (0..samples.len()).map(|_| samples[rng.gen_range(0..samples.len())]).collect();
+        values.push(estimator(&resample));
+    }
+    let average = mean(&values);
+    let variance = values.iter().map(|x| (x - average).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    return Estimate{mean: estimator(data), error: variance.sqrt()};
+}
+
+//mean and standard error across replicas of series sampled at the same times
+pub fn replica_band(series: &[Vec<(i32, f64)>]) -> Vec<(i32, f64, f64)> {
+    let length = series.iter().map(|s| s.len()).min().unwrap_or(0);
+    let mut band = vec![];
+    for i in 0..length {
+        let values: Vec<f64> = series.iter().map(|s| s[i].1).collect();
+        let average = mean(&values);
+        let mut error = 0.0;
+        if values.len() > 1 {
+            let n = values.len() as f64;
+            error = (values.iter().map(|x| (x - average).powi(2)).sum::<f64>() / (n - 1.0) / n).sqrt();
+        }
+        band.push((series[0][i].0, average, error));
+    }
+    return band;
+}
+
+//counts of the values of an observable in bins of equal width, bin k holding [k width, (k + 1) width). the bins are
+//only created as values fall in them, so the range does not have to be known in advance
+#[derive(Clone)]
+pub struct Histogram {
+    pub width: f64,
+    counts: BTreeMap<i64, u64>,
+}
+
+impl Histogram {
+    pub fn new(width: f64) -> Self {
+        assert!(width > 0.0, "the bins of a histogram must have a positive width");
+        return Histogram{width, counts: BTreeMap::new()};
+    }
+
+    pub fn add(&mut self, value: f64) {
+        *self.counts.entry((value / self.width).floor() as i64).or_insert(0) += 1;
+    }
+
+    //adds the counts of other, which must have bins of the same width
+    pub fn merge(&mut self, other: &Histogram) {
+        assert_eq!(self.width, other.width, "only histograms with bins of the same width can be merged");
+        for (&bin, &count) in &other.counts {
+            *self.counts.entry(bin).or_insert(0) += count;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        return self.counts.values().sum();
+    }
+
+    //every bin from the lowest to the highest one reached, empty ones included, as (lower edge, upper edge, count)
+    pub fn bins(&self) -> Vec<(f64, f64, u64)> {
+        let (Some(&first), Some(&last)) = (self.counts.keys().next(), self.counts.keys().next_back()) else {
+            return vec![];
+        };
+        return (first..=last).map(|bin| (bin as f64 * self.width, (bin + 1) as f64 * self.width, self.counts.get(&bin).copied().unwrap_or(0))).collect();
+    }
+
+    //the bins with the fraction of the values that fell in each of them
+    pub fn probabilities(&self) -> Vec<(f64, f64, f64)> {
+        let total = self.total().max(1) as f64;
+        return self.bins().into_iter().map(|(lower, upper, count)| (lower, upper, count as f64 / total)).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histograms_fill_the_gaps_between_bins() {
+        let mut histogram = Histogram::new(2.0);
+        for value in [-0.5, 0.0, 1.9, 6.0, 7.5] {
+            histogram.add(value);
+        }
+        let mut other = Histogram::new(2.0);
+        other.add(3.0);
+        histogram.merge(&other);
+        assert_eq!(histogram.bins(), vec![(-2.0, 0.0, 1), (0.0, 2.0, 2), (2.0, 4.0, 1), (4.0, 6.0, 0), (6.0, 8.0, 2)]);
+        assert_eq!(histogram.probabilities().iter().map(|bin| bin.2).sum::<f64>(), 1.0);
+        assert!(Histogram::new(0.1).bins().is_empty());
+    }
+
+    #[test]
+    fn combined_errors_include_the_scatter_between_replicas() {
+        let agreeing = [Estimate{mean: 1.0, error: 0.1}, Estimate{mean: 1.0, error: 0.1}];
+        assert!((Estimate::combine(&agreeing).error - 0.1 / 2f64.sqrt()).abs() < 1e-12);
+        let disagreeing = [Estimate{mean: 0.0, error: 0.01}, Estimate{mean: 2.0, error: 0.01}];
+        let combined = Estimate::combine(&disagreeing);
+        assert_eq!(combined.mean, 1.0);
+        assert!((combined.error - 1.0).abs() < 1e-12);
+    }
+}
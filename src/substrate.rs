@@ -0,0 +1,109 @@
+use crate::lattice::Lattice;
+use crate::random::{Backend, Generator};
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::fmt;
+
+//distribution of the random on-site energies, in the same units as the field
+#[derive(Clone, Copy, PartialEq)]
+pub enum SiteEnergies {
+    //every site has the same energy
+    Flat,
+    //uniform between -width / 2 and width / 2
+    Uniform(f64),
+    //gaussian with zero mean and the given standard deviation
+    Gaussian(f64),
+}
+
+impl fmt::Display for SiteEnergies {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            SiteEnergies::Flat => write!(f, "flat"),
+            SiteEnergies::Uniform(width) => write!(f, "uniform of width {:.3}", width),
+            SiteEnergies::Gaussian(sigma) => write!(f, "gaussian of sigma {:.3}", sigma),
+        };
+    }
+}
+
+//quenched disorder of the substrate: the fraction of the sites that are permanently blocked, the fraction holding
+//a pinned impurity particle and the distribution of the on-site energies. betaj is the temperature, this is the disorder
+#[derive(Clone, Copy, PartialEq)]
+pub struct Substrate {
+    pub blocked: f64,
+    pub impurities: f64,
+    pub site_energies: SiteEnergies,
+}
+
+impl Substrate {
+    //a perfect substrate, the default of every board
+    pub fn clean() -> Self {
+        return Substrate{blocked: 0.0, impurities: 0.0, site_energies: SiteEnergies::Flat};
+    }
+
+    pub fn is_clean(&self) -> bool {
+        return *self == Substrate::clean();
+    }
+
+    //draws the realisation of this substrate given by seed on a board of size x size of lattice. it draws from stream 0 of
+    //chacha12 from seed, so the same seed gives the same substrate whatever the thermal generator of the board. the fractions are of the sites of
+    //the lattice, blocked sites are drawn first and impurities from the remaining ones, so raising the impurity fraction
+    //keeps the impurities of a lower one
+    pub fn realise(&self, lattice: &dyn Lattice, size: u16, seed: u64) -> Realisation {
+        let mut rng = Generator::new(Backend::ChaCha12, seed, 0);
+        let mut cells: Vec<(usize, usize)> = (0..size as usize).flat_map(|x| (0..size as usize).map(move |y| (x, y)))
+            .filter(|&(x, y)| lattice.is_site(x as i16, y as i16))
+            .collect();
+        cells.shuffle(&mut rng);
+        let count = |fraction: f64| ((cells.len() as f64 * fraction).round() as usize).min(cells.len());
+        let blocked = count(self.blocked);
+        let impurities = count(self.impurities).min(cells.len() - blocked);
+
+        let mut sites = vec![vec![Site{blocked: false, pinned: false, energy: 0.0}; size as usize]; size as usize];
+        for &(x, y) in &cells[..blocked] {
+            sites[x][y].blocked = true;
+        }
+        for &(x, y) in &cells[blocked..blocked + impurities] {
+            sites[x][y].pinned = true;
+        }
+        for column in sites.iter_mut() {
+            for site in column.iter_mut() {
+                site.energy = match self.site_energies {
+                    SiteEnergies::Flat => 0.0,
+                    SiteEnergies::Uniform(width) => width * (rng.gen::<f64>() - 0.5),
+                    //box-muller, 1 - u keeps the logarithm finite
+                    SiteEnergies::Gaussian(sigma) => {
+                        let radius = (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt();
+                        sigma * radius * (2.0 * std::f64::consts::PI * rng.gen::<f64>()).cos()
+                    }
+                };
+            }
+        }
+        return Realisation{substrate: *self, seed, sites};
+    }
+}
+
+impl fmt::Display for Substrate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "blocked {:.3}, impurities {:.3}, {} site energies", self.blocked, self.impurities, self.site_energies);
+    }
+}
+
+//one site of the substrate
+#[derive(Clone, Copy)]
+pub struct Site {
+    //no particle can ever sit here
+    pub blocked: bool,
+    //holds an impurity particle that interacts like a particle of species 0 but never moves
+    pub pinned: bool,
+    //added to the energy of a particle sitting here
+    pub energy: f64,
+}
+
+//a substrate drawn from a seed, indexed like the grid of the board
+#[derive(Clone)]
+pub struct Realisation {
+    pub substrate: Substrate,
+    pub seed: u64,
+    pub sites: Vec<Vec<Site>>,
+}
@@ -0,0 +1,113 @@
+use crate::lattice::{self, Lattice};
+
+use std::fmt;
+
+//commensurate orderings of the triangular lattice that get_order can measure, each one filling a single sublattice
+//of a superlattice. sublattices are labelled on the axial coordinates i = x - floor(y / 2), j = y of the offset rows
+#[derive(Clone, Copy, PartialEq)]
+pub enum Superstructure {
+    //three sublattices, one of them filled at fill 1/3
+    Sqrt3,
+    //the sqrt(3) x sqrt(3) sublattices with two of them filled at fill 2/3, measured on the vacancies
+    Sqrt3Complement,
+    //four sublattices, one of them filled at fill 1/4
+    TwoByTwo,
+    //seven sublattices, one of them filled at fill 1/7
+    Sqrt7,
+}
+
+impl Superstructure {
+    pub fn all() -> [Superstructure; 4] {
+        return [Superstructure::Sqrt3, Superstructure::Sqrt3Complement, Superstructure::TwoByTwo, Superstructure::Sqrt7];
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Superstructure::Sqrt3 => "sqrt3",
+            Superstructure::Sqrt3Complement => "sqrt3 complement",
+            Superstructure::TwoByTwo => "2x2",
+            Superstructure::Sqrt7 => "sqrt7",
+        };
+    }
+
+    pub fn by_name(name: &str) -> Option<Superstructure> {
+        return Superstructure::all().into_iter().find(|superstructure| superstructure.name() == name);
+    }
+
+    //the fill at which the ordering is perfect
+    pub fn fill(&self) -> f64 {
+        return match self {
+            Superstructure::Sqrt3 => 1.0 / 3.0,
+            Superstructure::Sqrt3Complement => 2.0 / 3.0,
+            Superstructure::TwoByTwo => 1.0 / 4.0,
+            Superstructure::Sqrt7 => 1.0 / 7.0,
+        };
+    }
+
+    //the ordering whose fill is closest to fill, the complement from fill 1/2 on
+    pub fn closest(fill: f64) -> Superstructure {
+        if fill >= 0.5 {
+            return Superstructure::Sqrt3Complement;
+        }
+        let mut closest = Superstructure::Sqrt3;
+        for superstructure in [Superstructure::TwoByTwo, Superstructure::Sqrt7] {
+            if (superstructure.fill() - fill).abs() < (closest.fill() - fill).abs() {
+                closest = superstructure;
+            }
+        }
+        return closest;
+    }
+
+    pub fn sublattice_count(&self) -> usize {
+        return match self {
+            Superstructure::Sqrt3 | Superstructure::Sqrt3Complement => 3,
+            Superstructure::TwoByTwo => 4,
+            Superstructure::Sqrt7 => 7,
+        };
+    }
+
+    //whether the ordering is seen on the empty sites rather than on the particles
+    pub fn on_vacancies(&self) -> bool {
+        return *self == Superstructure::Sqrt3Complement;
+    }
+
+    //nearest neighbour steps between the closest sites of a sublattice, the distance at which domains are joined
+    pub fn reach(&self) -> usize {
+        return match self {
+            Superstructure::Sqrt7 => 3,
+            _ => 2,
+        };
+    }
+
+    //sublattice of the cell x, y of the triangular lattice. the sqrt(3) labels are the ones of the lattice, the 2x2 ones
+    //close for sizes that are a multiple of 4 and the sqrt(7) ones, constant along (2, 1) and (-1, 3), for multiples of 14
+    pub fn sublattice(&self, x: i16, y: i16) -> u8 {
+        let i = x as i32 - (y as i32).div_euclid(2);
+        let j = y as i32;
+        return match self {
+            Superstructure::Sqrt3 | Superstructure::Sqrt3Complement => (i - j).rem_euclid(3) as u8,
+            Superstructure::TwoByTwo => (i.rem_euclid(2) + 2 * j.rem_euclid(2)) as u8,
+            Superstructure::Sqrt7 => (i + 5 * j).rem_euclid(7) as u8,
+        };
+    }
+
+    //the ordering get_order measures on lattice at fill: the chosen one, or the closest to the fill when none is chosen.
+    //superstructures only exist on the triangular lattice, the others are measured on their own sublattices
+    pub fn measured(lattice: &dyn Lattice, fill: f64, chosen: Option<Superstructure>) -> Option<Superstructure> {
+        if lattice.name() != lattice::TRIANGULAR.name() {
+            return None;
+        }
+        return Some(chosen.unwrap_or(Superstructure::closest(fill)));
+    }
+}
+
+impl fmt::Display for Superstructure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Superstructure::Sqrt3 => write!(f, "sqrt(3) x sqrt(3)"),
+            Superstructure::Sqrt3Complement => write!(f, "complementary sqrt(3) x sqrt(3)"),
+            Superstructure::TwoByTwo => write!(f, "2 x 2"),
+            Superstructure::Sqrt7 => write!(f, "sqrt(7) x sqrt(7)"),
+        };
+    }
+}
@@ -0,0 +1,255 @@
+use crate::HexBoard;
+use crate::hexboard::{Boundary, InitialState};
+use crate::statistics::Estimate;
+use crate::substrate::{SiteEnergies, Substrate};
+use crate::lattice::{self, Lattice};
+use crate::superstructure::Superstructure;
+use crate::grid::Storage;
+use crate::random::{Backend, Generator};
+use crate::error::Result;
+
+use std::fmt;
+use std::ops::Range;
+
+//every parameter needed to build a board
+#[derive(Clone, Copy)]
+pub struct Parameters {
+    pub size: u16,
+    pub fill: f64,
+    pub betaj: f64,
+    pub coupling: f64,
+    pub field: f64,
+    pub boundary: Boundary,
+    pub initial: InitialState,
+    //the quenched disorder, whose realisations are drawn by the batch from seeds of their own
+    pub substrate: Substrate,
+    pub lattice: &'static dyn Lattice,
+    //the ordering measured at every point, None picks the one closest to the fill of the point
+    pub superstructure: Option<Superstructure>,
+    //how the boards keep their cells, which changes the memory and speed of a run but not its results
+    pub storage: Storage,
+}
+
+impl Parameters {
+    pub fn new(size: u16, fill: f64, betaj: f64) -> Self {
+        return Parameters{size, fill, betaj, coupling: 1.0, field: 0.0, boundary: Boundary::Periodic, initial: InitialState::Random, substrate: Substrate::clean(), lattice: &lattice::TRIANGULAR, superstructure: None, storage: Storage::Cells};
+    }
+
+    //same parameters, started from another configuration
+    pub fn starting_from(&self, initial: InitialState) -> Self {
+        return Parameters{initial, ..*self};
+    }
+
+    //same parameters, on another substrate
+    pub fn on_substrate(&self, substrate: Substrate) -> Self {
+        return Parameters{substrate, ..*self};
+    }
+
+    //same parameters, on another lattice
+    pub fn on_lattice(&self, lattice: &'static dyn Lattice) -> Self {
+        return Parameters{lattice, ..*self};
+    }
+
+    //same parameters, with the cells of the boards kept in storage
+    pub fn stored_as(&self, storage: Storage) -> Self {
+        return Parameters{storage, ..*self};
+    }
+
+    //builds an empty board with these parameters, initial is only used once the board is initialized. fails on the
+    //periodic boards of odd size that set_lattice rejects
+    pub fn build(&self, rng: Generator) -> Result<HexBoard> {
+        let mut board = HexBoard::with_storage(self.size, rng, self.betaj, self.fill as f32, self.storage);
+        board.set_coupling(self.coupling);
+        board.set_field(self.field);
+        board.set_boundary(self.boundary);
+        board.set_lattice(self.lattice)?;
+        board.set_superstructure(self.superstructure);
+        return Ok(board);
+    }
+}
+
+impl fmt::Display for Parameters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let boundary = match self.boundary {
+            Boundary::Periodic => "periodic",
+            Boundary::Open => "open",
+        };
+        write!(
+            f, "size {}, fill {:.3}, betaj {:.3}, coupling {:.3}, field {:.3}, {} boundary",
+            self.size, self.fill, self.betaj, self.coupling, self.field, boundary,
+        )?;
+        if self.initial != InitialState::Random {
+            write!(f, ", {}", self.initial)?;
+        }
+        if !self.substrate.is_clean() {
+            write!(f, ", {}", self.substrate)?;
+        }
+        if self.lattice.name() != lattice::TRIANGULAR.name() {
+            write!(f, ", {} lattice", self.lattice.name())?;
+        }
+        if let Some(superstructure) = self.superstructure {
+            write!(f, ", {} order", superstructure)?;
+        }
+        if self.storage != Storage::Cells {
+            write!(f, ", {}", self.storage)?;
+        }
+        return Ok(());
+    }
+}
+
+//a parameter that can be varied by a sweep
+#[derive(Clone, Copy)]
+pub enum Parameter {
+    Size,
+    Fill,
+    Betaj,
+    Coupling,
+    Field,
+    //0 for periodic and 1 for open boundaries
+    Boundary,
+    //fraction of blocked sites
+    Blocked,
+    //fraction of sites holding a pinned impurity
+    Impurities,
+    //width or standard deviation of the on-site energies, a flat substrate becomes uniform
+    SiteEnergies,
+}
+
+impl Parameter {
+    //returns parameters with this parameter set to value
+    pub fn apply(&self, parameters: Parameters, value: f64) -> Parameters {
+        let mut changed = parameters;
+        match self {
+            Parameter::Size => changed.size = value.round() as u16,
+            Parameter::Fill => changed.fill = value,
+            Parameter::Betaj => changed.betaj = value,
+            Parameter::Coupling => changed.coupling = value,
+            Parameter::Field => changed.field = value,
+            Parameter::Boundary => changed.boundary = if value >= 0.5 { Boundary::Open } else { Boundary::Periodic },
+            Parameter::Blocked => changed.substrate.blocked = value,
+            Parameter::Impurities => changed.substrate.impurities = value,
+            Parameter::SiteEnergies => changed.substrate.site_energies = match parameters.substrate.site_energies {
+                SiteEnergies::Gaussian(_) => SiteEnergies::Gaussian(value),
+                _ => SiteEnergies::Uniform(value),
+            },
+        }
+        return changed;
+    }
+
+    pub fn name(&self) -> &str {
+        return match self {
+            Parameter::Size => "size",
+            Parameter::Fill => "fill",
+            Parameter::Betaj => "betaj",
+            Parameter::Coupling => "coupling",
+            Parameter::Field => "field",
+            Parameter::Boundary => "boundary",
+            Parameter::Blocked => "blocked",
+            Parameter::Impurities => "impurities",
+            Parameter::SiteEnergies => "site energies",
+        };
+    }
+}
+
+//values taken by a swept parameter. linear and log ranges give steps values starting at min,
+//with the same spacing that would reach max after one more step
+pub enum Values {
+    Linear { min: f64, max: f64, steps: usize },
+    Log { min: f64, max: f64, steps: usize },
+    List(Vec<f64>),
+}
+
+impl Values {
+    pub fn get(&self) -> Vec<f64> {
+        return match self {
+            Values::Linear { min, max, steps } => {
+                let step = (max - min) / *steps as f64;
+                (0..*steps).map(|i| min + i as f64 * step).collect()
+            }
+            Values::Log { min, max, steps } => {
+                let ratio = (max / min).powf(1.0 / *steps as f64);
+                (0..*steps).map(|i| min * ratio.powi(i as i32)).collect()
+            }
+            Values::List(values) => values.clone(),
+        };
+    }
+}
+
+//a one or two dimensional sweep of parameters around a base configuration
+pub struct Sweep {
+    pub base: Parameters,
+    pub axes: Vec<(Parameter, Vec<f64>)>,
+}
+
+impl Sweep {
+    pub fn new(base: Parameters, parameter: Parameter, values: Values) -> Self {
+        return Sweep{base, axes: vec![(parameter, values.get())]};
+    }
+
+    //sweeps every combination of the values of two parameters, the second one varying fastest
+    pub fn grid(base: Parameters, first: (Parameter, Values), second: (Parameter, Values)) -> Self {
+        return Sweep{base, axes: vec![(first.0, first.1.get()), (second.0, second.1.get())]};
+    }
+
+    //parameters of every point of the sweep, with the swept values that produced them
+    pub fn points(&self) -> Vec<(Parameters, Vec<f64>)> {
+        let mut points: Vec<(Parameters, Vec<f64>)> = vec![(self.base, vec![])];
+        for (parameter, values) in &self.axes {
+            let mut expanded = vec![];
+            for (parameters, coordinates) in &points {
+                for &value in values {
+                    let mut point_coordinates = coordinates.clone();
+                    point_coordinates.push(value);
+                    expanded.push((parameter.apply(*parameters, value), point_coordinates));
+                }
+            }
+            points = expanded;
+        }
+        return points;
+    }
+
+    //label of a point, made of the names and values of the swept parameters
+    pub fn label(&self, coordinates: &[f64]) -> String {
+        let mut parts: Vec<String> = vec![];
+        for ((parameter, _), value) in self.axes.iter().zip(coordinates.iter()) {
+            parts.push(format!("{} {}", parameter.name(), (value * 1e4).round() / 1e4));
+        }
+        return parts.join(", ");
+    }
+}
+
+//structured result of one point of a sweep
+pub struct PointRecord {
+    pub parameters: Parameters,
+    pub coordinates: Vec<f64>,
+    pub psi: Estimate,
+    pub energy: Estimate,
+    pub specific_heat: Estimate,
+    pub susceptibility: Estimate,
+    //mean sublattice fractions, sorted from the most to the least occupied sublattice of every sample, one per sublattice of the lattice
+    pub order: Vec<f64>,
+    pub acceptance_rate: f64,
+    //the random generator of the replicas, replica r drew from stream streams.start + r of seed
+    pub backend: Backend,
+    pub seed: u64,
+    pub streams: Range<u64>,
+}
+
+impl PointRecord {
+    //the backend, seed and streams of the replicas
+    pub fn generator(&self) -> String {
+        return format!("{} seed {} streams {} to {}", self.backend, self.seed, self.streams.start, self.streams.end - 1);
+    }
+}
+
+impl fmt::Display for PointRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let order: Vec<String> = self.order.iter().map(|fraction| format!("{:.3}", fraction)).collect();
+        return write!(
+            f, "{}: |psi| {:.4} +- {:.4}, energy {:.2} +- {:.2}, C {:.4} +- {:.4}, chi {:.4} +- {:.4}, order [{}], acceptance {:.4}, {}",
+            self.parameters, self.psi.mean, self.psi.error, self.energy.mean, self.energy.error,
+            self.specific_heat.mean, self.specific_heat.error, self.susceptibility.mean, self.susceptibility.error,
+            order.join(", "), self.acceptance_rate, self.generator(),
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use crate::HexBoard;
+
+//keeps the identity of every particle and its unwrapped position, so hops across the periodic boundary are not lost
+#[derive(Clone)]
+pub struct Tracker {
+    pub(crate) ids: Vec<Vec<Option<usize>>>,
+    pub start: Vec<(f64, f64)>,
+    pub positions: Vec<(f64, f64)>,
+}
+
+impl Tracker {
+
+    //gives an id to every particle currently on the board
+    pub fn new(board: &HexBoard) -> Self {
+        let mut ids = vec![vec![None; board.size as usize]; board.size as usize];
+        let mut positions: Vec<(f64, f64)> = vec![];
+        for x in 0..board.size {
+            for y in 0..board.size {
+                if board.is_particle(board.get_cell(x as i16, y as i16)) {
+                    ids[x as usize][y as usize] = Some(positions.len());
+                    positions.push(board.get_position(x as i16, y as i16));
+                }
+            }
+        }
+        return Tracker{ids, start: positions.clone(), positions};
+    }
+
+    //moves the particle at from to the free cell to, shifting its unwrapped position by the displacement of the hop in the plane
+    pub fn hop(&mut self, from: (u16, u16), to: (u16, u16), displacement: (f64, f64)) {
+        if let Some(id) = self.ids[from.0 as usize][from.1 as usize].take() {
+            self.positions[id].0 += displacement.0;
+            self.positions[id].1 += displacement.1;
+            self.ids[to.0 as usize][to.1 as usize] = Some(id);
+        }
+    }
+
+    //mean squared displacement of all particles from their starting positions
+    pub fn msd(&self) -> f64 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+        let mut total = 0.0;
+        for (position, start) in self.positions.iter().zip(self.start.iter()) {
+            let dx = position.0 - start.0;
+            let dy = position.1 - start.1;
+            total += dx * dx + dy * dy;
+        }
+        return total / self.positions.len() as f64;
+    }
+}
+
+//tracer diffusion coefficient from a msd timeseries, using MSD = 4Dt in two dimensions.
+//the slope is fitted on the second half of the series, after the initial transient
+pub fn diffusion_coefficient(msd: &[(i32, f64)]) -> f64 {
+    let tail = &msd[msd.len() / 2..];
+    if tail.len() < 2 {
+        return 0.0;
+    }
+    let n = tail.len() as f64;
+    let mean_t = tail.iter().map(|p| p.0 as f64).sum::<f64>() / n;
+    let mean_msd = tail.iter().map(|p| p.1).sum::<f64>() / n;
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    for point in tail {
+        covariance += (point.0 as f64 - mean_t) * (point.1 - mean_msd);
+        variance += (point.0 as f64 - mean_t).powi(2);
+    }
+    if variance == 0.0 {
+        return 0.0;
+    }
+    return covariance / variance / 4.0;
+}
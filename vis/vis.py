@@ -2,7 +2,7 @@ import pandas as pd
 import matplotlib.pyplot as plt
 
 # Read the CSV file
-data = pd.read_csv('data.csv')
+data = pd.read_csv('data.csv', comment='#')
 
 size = 30
 # Extract the x and y values